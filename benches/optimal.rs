@@ -0,0 +1,46 @@
+//! Times the optimal solver on one thread and on every core and reports the speedup. That both
+//! give the same assignments is checked by the `threads` test. Run with
+//! `cargo bench --bench optimal`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use obamify::core::calculate::util::set_thread_limit;
+use obamify::core::{Algorithm, GenerationSettings, JobControl, ProgressMsg, process};
+use uuid::Uuid;
+
+use common::source;
+
+fn run(sidelen: u32, threads: usize) -> Duration {
+    let mut settings = GenerationSettings::default(Uuid::nil(), "bench".to_owned());
+    settings.sidelen = sidelen;
+    settings.algorithm = Algorithm::OPTIMAL;
+    set_thread_limit(threads);
+
+    let start = Instant::now();
+    process(
+        source(),
+        settings,
+        &mut |_: ProgressMsg| {},
+        Arc::new(JobControl::default()),
+    )
+    .unwrap();
+    start.elapsed()
+}
+
+fn main() {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    // both are wide enough for the solver to split its loops over threads
+    for sidelen in [96, 128] {
+        let sequential = run(sidelen, 1);
+        let parallel = run(sidelen, 0);
+        let speedup = sequential.as_secs_f64() / parallel.as_secs_f64();
+        println!(
+            "optimal {sidelen}×{sidelen}: 1 thread {sequential:.2?}, {cores} threads \
+             {parallel:.2?} ({speedup:.2}× faster)"
+        );
+    }
+}
@@ -1,11 +1,5 @@
-mod calculate;
 mod gif_recorder;
 mod gui;
-mod morph_sim;
-mod preset;
-
-#[cfg(target_arch = "wasm32")]
-pub use crate::app::calculate::worker::worker_entry;
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::mpsc;
@@ -15,29 +9,16 @@ use std::{
 };
 
 #[cfg(not(target_arch = "wasm32"))]
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicBool;
 
 use bytemuck::{Pod, Zeroable};
 use eframe::CreationContext;
 use egui_wgpu::{self, wgpu};
-#[cfg(not(target_arch = "wasm32"))]
 use uuid::Uuid;
 use wgpu::util::DeviceExt;
 
 //const INVALID_ID: u32 = 0xFFFF_FFFF;
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
-pub struct SeedPos {
-    xy: [f32; 2],
-}
-
-#[repr(C)]
-#[derive(Clone, Copy, Pod, Zeroable)]
-pub struct SeedColor {
-    rgba: [f32; 4],
-}
-
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 struct ParamsCommon {
@@ -67,8 +48,14 @@ pub enum GuiMode {
     Draw,
 }
 
-use crate::app::{calculate::ProgressMsg, morph_sim::Sim, preset::UnprocessedPreset};
-use crate::app::{calculate::util::GenerationSettings, preset::Preset};
+use crate::core::{
+    calculate::{self, JobMsg, ProgressMsg, util::GenerationSettings},
+    morph_sim::{self, SeedColor, SeedPos, Sim},
+    permutation,
+    preset::{Preset, UnprocessedPreset},
+    storage,
+    validate::validate_preset,
+};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::closure::Closure;
@@ -84,15 +71,17 @@ pub struct ObamifyApp {
     seed_count: u32,
 
     #[cfg(not(target_arch = "wasm32"))]
-    progress_tx: mpsc::SyncSender<ProgressMsg>,
+    progress_tx: mpsc::SyncSender<JobMsg>,
     #[cfg(not(target_arch = "wasm32"))]
-    progress_rx: mpsc::Receiver<ProgressMsg>,
+    progress_rx: mpsc::Receiver<JobMsg>,
+    /// The job whose messages are applied; everything else that arrives is dropped.
+    current_job: Option<Uuid>,
 
     #[cfg(target_arch = "wasm32")]
     worker: Option<Worker>,
 
     #[cfg(target_arch = "wasm32")]
-    inbox: Vec<ProgressMsg>,
+    inbox: Vec<JobMsg>,
 
     gif_recorder: gif_recorder::GifRecorder,
     sim: Sim,
@@ -159,7 +148,7 @@ pub struct ObamifyApp {
 
     gui: gui::GuiState,
     #[cfg(not(target_arch = "wasm32"))]
-    current_drawing_id: Arc<AtomicU32>,
+    drawing_cancel: Option<Arc<AtomicBool>>,
     current_filter_mode: wgpu::FilterMode,
 }
 
@@ -231,9 +220,16 @@ impl ObamifyApp {
         source: Preset,
         change_index: usize,
     ) {
-        let (seed_count, seeds, colors, sim) = morph_sim::init_image(self.size.0, source);
-        self.apply_sim_init(device, queue, seed_count, seeds, colors, sim);
-        self.gui.current_preset = change_index;
+        let name = source.inner.name.clone();
+        match morph_sim::init_image(self.size.0, source) {
+            Ok((seed_count, seeds, colors, sim)) => {
+                self.apply_sim_init(device, queue, seed_count, seeds, colors, sim);
+                self.gui.current_preset = change_index;
+            }
+            Err(e) => self
+                .gui
+                .show_error(format!("can't show preset \"{name}\": {e}")),
+        }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -258,23 +254,66 @@ impl ObamifyApp {
         egui_extras::install_image_loaders(&cc.egui_ctx);
 
         // get all folders in ../presets
-        let presets: Vec<Preset> = if let Some(storage) = cc.storage {
-            eframe::get_value(storage, "presets").unwrap_or(get_presets())
-        } else {
-            get_presets()
+        let mut storage_recovery = None;
+        let stored = |key: &str| {
+            cc.storage
+                .and_then(|s| s.get_string(key))
+                .filter(|raw| !raw.is_empty())
+        };
+        // presets made in an earlier session while the stored ones couldn't be read
+        let kept_apart = stored(storage::RECOVERY_PRESETS_KEY)
+            .and_then(|raw| storage::decode_presets(&raw).ok());
+        let mut presets: Vec<Preset> = match stored(storage::PRESETS_KEY) {
+            Some(raw) => match storage::decode_presets(&raw) {
+                Ok(mut presets) => {
+                    // the stored presets can be read again, e.g. after an update: add the ones
+                    // made in the meantime
+                    for preset in kept_apart.into_iter().flatten() {
+                        let id = preset.settings.as_ref().map(|s| s.id);
+                        let known = presets
+                            .iter()
+                            .any(|p| p.settings.as_ref().map(|s| s.id) == id);
+                        if id.is_some() && !known {
+                            presets.push(preset);
+                        }
+                    }
+                    presets
+                }
+                Err(e) => {
+                    log::error!("{e}");
+                    storage_recovery = Some(gui::StorageRecovery::new(raw, e));
+                    kept_apart.unwrap_or_else(get_presets)
+                }
+            },
+            None => get_presets(),
         };
 
+        // broken presets stay in the list (picking one shows the error), but never start
+        let mut valid_presets = Vec::new();
+        for (i, preset) in presets.iter().enumerate() {
+            match validate_preset(preset) {
+                Ok(()) => valid_presets.push(i),
+                Err(e) => log::warn!("preset \"{}\" is invalid: {e}", preset.inner.name),
+            }
+        }
+        if valid_presets.is_empty() {
+            valid_presets.extend(presets.len()..presets.len() + get_presets().len());
+            presets.extend(get_presets());
+        }
+
         #[cfg(target_arch = "wasm32")]
-        let random_preset = (js_sys::Math::random() * (presets.len() as f64)) as usize;
+        let random_preset = (js_sys::Math::random() * (valid_presets.len() as f64)) as usize;
 
         #[cfg(not(target_arch = "wasm32"))]
         let random_preset = frand::Rand::with_seed(
             std::time::SystemTime::now().elapsed().unwrap().as_nanos() as u64,
         )
-        .gen_range(0..presets.len() as u64) as usize;
+        .gen_range(0..valid_presets.len() as u64) as usize;
+        let random_preset = valid_presets[random_preset];
 
         let (seed_count, seeds, colors, sim) =
-            morph_sim::init_image(size.0, presets[random_preset].clone());
+            morph_sim::init_image(size.0, presets[random_preset].clone())
+                .expect("preset was validated");
 
         // === Buffers ===
         let seed_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -737,7 +776,7 @@ impl ObamifyApp {
         });
 
         #[cfg(not(target_arch = "wasm32"))]
-        let (progress_tx, progress_rx) = mpsc::sync_channel::<ProgressMsg>(1);
+        let (progress_tx, progress_rx) = mpsc::sync_channel::<JobMsg>(1);
 
         Self {
             size,
@@ -785,14 +824,15 @@ impl ObamifyApp {
             progress_tx,
             #[cfg(not(target_arch = "wasm32"))]
             progress_rx,
+            current_job: None,
             gif_recorder: gif_recorder::GifRecorder::new(),
             preview_image: None,
             #[cfg(not(target_arch = "wasm32"))]
             stroke_count: 0,
-            gui: gui::GuiState::default(presets, random_preset),
+            gui: gui::GuiState::default(presets, random_preset, storage_recovery),
             frame_count: 0,
             #[cfg(not(target_arch = "wasm32"))]
-            current_drawing_id: Arc::new(AtomicU32::new(0)),
+            drawing_cancel: None,
             #[cfg(target_arch = "wasm32")]
             worker: None,
             #[cfg(target_arch = "wasm32")]
@@ -801,24 +841,37 @@ impl ObamifyApp {
         }
     }
 
+    /// Next message from the current job, dropping any from other jobs on the way.
     pub fn get_latest_msg(&mut self) -> Option<ProgressMsg> {
-        #[cfg(target_arch = "wasm32")]
-        {
-            self.inbox.pop()
-        }
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            match self.progress_rx.try_recv() {
-                Ok(msg) => Some(msg),
-                Err(mpsc::TryRecvError::Empty) => None,
+        loop {
+            #[cfg(target_arch = "wasm32")]
+            let job_msg = self.inbox.pop()?;
+            #[cfg(not(target_arch = "wasm32"))]
+            let job_msg = match self.progress_rx.try_recv() {
+                Ok(msg) => msg,
+                Err(mpsc::TryRecvError::Empty) => return None,
                 Err(mpsc::TryRecvError::Disconnected) => {
                     eprintln!("progress channel disconnected");
-                    None
+                    return None;
                 }
+            };
+
+            if job_msg.job_id.is_nil() || Some(job_msg.job_id) == self.current_job {
+                return Some(job_msg.msg);
             }
+            log::debug!(
+                "dropping {} message from stale job {}",
+                job_msg.msg.typ(),
+                job_msg.job_id
+            );
         }
     }
 
+    /// Makes `job_id` the job whose messages are applied from now on.
+    pub fn set_current_job(&mut self, job_id: Uuid) {
+        self.current_job = Some(job_id);
+    }
+
     #[cfg(target_arch = "wasm32")]
     fn ensure_worker(&mut self, _ctx: &egui::Context) {
         if self.worker.is_some() {
@@ -904,9 +957,9 @@ impl ObamifyApp {
 
         // Receive progress messages
         {
-            let inbox_ptr: *mut Vec<ProgressMsg> = &mut self.inbox;
+            let inbox_ptr: *mut Vec<JobMsg> = &mut self.inbox;
             let onmessage = Closure::wrap(Box::new(move |e: web_sys::MessageEvent| {
-                if let Ok(msg) = serde_wasm_bindgen::from_value::<ProgressMsg>(e.data()) {
+                if let Ok(msg) = serde_wasm_bindgen::from_value::<JobMsg>(e.data()) {
                     // SAFETY: single-threaded; worker posts to main thread
                     unsafe {
                         (*inbox_ptr).push(msg);
@@ -922,6 +975,7 @@ impl ObamifyApp {
 
     #[cfg(target_arch = "wasm32")]
     fn start_job(&mut self, src: UnprocessedPreset, settings: GenerationSettings) {
+        self.set_current_job(settings.id);
         if let Some(w) = &self.worker {
             let req = calculate::worker::WorkerReq::Process {
                 source: src,
@@ -932,6 +986,87 @@ impl ObamifyApp {
         }
     }
 
+    /// Like [`Self::start_job`], but continues from the preset's assignments.
+    #[cfg(target_arch = "wasm32")]
+    fn start_refine_job(&mut self, preset: Preset, settings: GenerationSettings) {
+        self.set_current_job(settings.id);
+        if let Some(w) = &self.worker {
+            let req = calculate::worker::WorkerReq::Refine { preset, settings };
+            let v = serde_wasm_bindgen::to_value(&req).unwrap();
+            w.post_message(&v).unwrap();
+        }
+    }
+
+    /// Like [`Self::start_job`], but carries on with a paused job.
+    #[cfg(target_arch = "wasm32")]
+    fn start_resume_job(&mut self, job: calculate::PausedJob) {
+        self.set_current_job(job.settings.id);
+        if let Some(w) = &self.worker {
+            let req = calculate::worker::WorkerReq::Resume { job };
+            let v = serde_wasm_bindgen::to_value(&req).unwrap();
+            w.post_message(&v).unwrap();
+        }
+    }
+
+    /// Runs `job` on a new thread as the current job, with its messages going to the progress
+    /// channel.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn_job(
+        &mut self,
+        job_id: Uuid,
+        control: Arc<calculate::util::JobControl>,
+        job: impl FnOnce(
+            &mut calculate::util::JobSender,
+            Arc<calculate::util::JobControl>,
+        ) -> Result<(), calculate::CalculateError>
+        + Send
+        + 'static,
+    ) {
+        use calculate::util::ProgressSink;
+
+        self.set_current_job(job_id);
+        let mut sink = calculate::util::JobSender {
+            job_id,
+            tx: self.progress_tx.clone(),
+        };
+        std::thread::spawn(move || match job(&mut sink, control) {
+            // a cancelled or paused job already said so
+            Ok(())
+            | Err(calculate::CalculateError::Cancelled | calculate::CalculateError::Paused) => {}
+            Err(err) => sink.send(ProgressMsg::Error(err)),
+        });
+    }
+
+    /// Asks the worker to stop the current job; it answers with `Cancelled` once it has.
+    #[cfg(target_arch = "wasm32")]
+    fn cancel_job(&mut self) {
+        if let (Some(w), Some(job_id)) = (&self.worker, self.current_job) {
+            let req = calculate::worker::WorkerReq::Cancel { job_id };
+            let v = serde_wasm_bindgen::to_value(&req).unwrap();
+            w.post_message(&v).unwrap();
+        }
+    }
+
+    /// Asks the worker to pause the current job; it answers with `Paused` and the job's state.
+    #[cfg(target_arch = "wasm32")]
+    fn pause_job(&mut self) {
+        if let (Some(w), Some(job_id)) = (&self.worker, self.current_job) {
+            let req = calculate::worker::WorkerReq::Pause { job_id };
+            let v = serde_wasm_bindgen::to_value(&req).unwrap();
+            w.post_message(&v).unwrap();
+        }
+    }
+
+    /// Asks the worker to end the current job early; it answers with `Done` and its best result.
+    #[cfg(target_arch = "wasm32")]
+    fn finish_job(&mut self) {
+        if let (Some(w), Some(job_id)) = (&self.worker, self.current_job) {
+            let req = calculate::worker::WorkerReq::Finish { job_id };
+            let v = serde_wasm_bindgen::to_value(&req).unwrap();
+            w.post_message(&v).unwrap();
+        }
+    }
+
     fn make_ids_texture(
         device: &wgpu::Device,
         size: (u32, u32),
@@ -1646,7 +1781,7 @@ impl ObamifyApp {
         // Keep the buffer for backward compatibility if needed elsewhere
         self.color_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("colors"),
-            contents: bytemuck::cast_slice(&*colors),
+            contents: bytemuck::cast_slice(&colors),
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
     }
@@ -1693,7 +1828,7 @@ impl ObamifyApp {
 
     #[cfg(not(target_arch = "wasm32"))]
     fn init_canvas(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        let blank = image::load_from_memory(include_bytes!("./app/calculate/blank.png"))
+        let blank = image::load_from_memory(include_bytes!("./core/calculate/blank.png"))
             .unwrap()
             .to_rgba8();
 
@@ -1707,17 +1842,18 @@ impl ObamifyApp {
         self.canvas_sim(device, queue, &source);
         self.gui.animate = true;
 
-        self.current_drawing_id
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.stop_drawing();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.drawing_cancel = Some(cancel.clone());
+        self.set_current_job(settings.id);
 
         std::thread::spawn({
             let tx = self.progress_tx.clone();
             let colors = Arc::clone(&self.colors);
             let pixel_data = Arc::clone(&self.pixeldata);
             let frame_count = self.frame_count;
-            let current_id = self.current_drawing_id.clone();
-            let my_id = current_id.load(std::sync::atomic::Ordering::SeqCst);
             let source = source.clone();
+            let job_id = settings.id;
             move || {
                 let result = calculate::drawing_process::drawing_process_genetic(
                     source,
@@ -1726,18 +1862,26 @@ impl ObamifyApp {
                     colors,
                     pixel_data,
                     frame_count,
-                    my_id,
-                    current_id,
+                    cancel,
                 );
                 match result {
-                    Ok(()) => {}
+                    Ok(()) | Err(calculate::CalculateError::Cancelled) => {}
                     Err(err) => {
-                        tx.send(ProgressMsg::Error(err.to_string())).ok();
+                        let msg = ProgressMsg::Error(err);
+                        tx.send(JobMsg { job_id, msg }).ok();
                     }
                 }
             }
         });
     }
+
+    /// Stops the drawing job of the current canvas, if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stop_drawing(&mut self) {
+        if let Some(cancel) = self.drawing_cancel.take() {
+            cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -1786,15 +1930,13 @@ macro_rules! include_presets {
                             height: img.height(),
                             source_img: img.into_raw(),
                         },
-                        assignments: include_str!(concat!("../presets/", $name, "/assignments.json"))
-                            .to_string()
-                            .strip_prefix('[')
-                            .unwrap()
-                            .strip_suffix(']')
-                            .unwrap()
-                            .split(',')
-                            .map(|s| s.parse().unwrap())
-                            .collect::<Vec<usize>>(),
+                        assignments: permutation::decode(include_bytes!(concat!(
+                            "../presets/",
+                            $name,
+                            "/assignments.bin"
+                        )))
+                        .unwrap(),
+                        settings: None,
                     }
                 }),*
             ]
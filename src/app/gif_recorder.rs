@@ -4,7 +4,7 @@ use std::sync::{Arc, atomic::AtomicBool};
 
 use color_quant::NeuQuant;
 
-use crate::{ObamifyApp, app::SeedColor};
+use crate::{ObamifyApp, core::morph_sim::SeedColor};
 
 pub const GIF_FRAMERATE: u32 = 8;
 pub const GIF_RESOLUTION: u32 = 400;
@@ -4,16 +4,25 @@ use super::DRAWING_ALPHA;
 use super::GuiMode;
 use super::ObamifyApp;
 use crate::app::DEFAULT_RESOLUTION;
-use crate::app::calculate;
-use crate::app::calculate::ProgressMsg;
-use crate::app::calculate::util::CropScale;
-use crate::app::calculate::util::GenerationSettings;
-use crate::app::calculate::util::SourceImg;
 use crate::app::gif_recorder::GIF_FRAMERATE;
 use crate::app::gif_recorder::GIF_RESOLUTION;
 use crate::app::gif_recorder::GifStatus;
-use crate::app::preset::Preset;
-use crate::app::preset::UnprocessedPreset;
+use crate::core::bundle;
+use crate::core::calculate;
+use crate::core::calculate::CalculateError;
+use crate::core::calculate::ProgressMsg;
+use crate::core::calculate::cost::{ColorSpace, CostModel, MAX_SIDELEN};
+use crate::core::calculate::importance::AutoWeights;
+use crate::core::calculate::solver;
+use crate::core::calculate::util::CropScale;
+use crate::core::calculate::util::GenerationSettings;
+use crate::core::calculate::util::SourceImg;
+use crate::core::calculate::util::{Algorithm, AnnealingSettings, JobControl};
+use crate::core::paused;
+use crate::core::preset::Preset;
+use crate::core::preset::UnprocessedPreset;
+use crate::core::storage::{self, StorageError};
+use crate::core::validate::validate_preset;
 use eframe::App;
 use eframe::Frame;
 use egui::Color32;
@@ -23,10 +32,12 @@ use egui::Window;
 use image::buffer::ConvertBuffer;
 use image::imageops;
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
 use uuid::Uuid;
 
+/// Above this resolution the settings window warns that generating takes long; the optimal
+/// algorithm's run time grows with the cube of the pixel count.
+const SLOW_SIDELEN: u32 = 256;
+
 // #[cfg(not(target_arch = "wasm32"))]
 // use std::thread as wasm_thread;
 
@@ -35,6 +46,24 @@ struct GuiImageCache {
     source_preview: Option<egui::TextureHandle>,
     target_preview: Option<egui::TextureHandle>,
     overlap_preview: Option<egui::TextureHandle>,
+    /// The custom target's automatic weights, with the crop and blend they were made for.
+    weights_preview: Option<((CropScale, AutoWeights), egui::TextureHandle)>,
+}
+
+pub(crate) struct StorageRecovery {
+    pub raw: String,
+    pub error: StorageError,
+    dismissed: bool,
+}
+
+impl StorageRecovery {
+    pub fn new(raw: String, error: StorageError) -> Self {
+        Self {
+            raw,
+            error,
+            dismissed: false,
+        }
+    }
 }
 
 pub(crate) struct GuiState {
@@ -47,25 +76,49 @@ pub(crate) struct GuiState {
     //pub fps_text: String,
     show_progress_modal: Option<Uuid>,
     last_progress: f32,
-    process_cancelled: Arc<AtomicBool>,
+    job_control: Arc<JobControl>,
+    /// Whether the running job's solver can be paused.
+    job_pauses: bool,
     //pub currently_processing: Option<Preset>,
     pub presets: Vec<Preset>,
     //pub current_settings: GenerationSettings,
     configuring_generation: Option<(SourceImg, GenerationSettings, GuiImageCache)>,
+    /// Assignments to continue from while the settings window is open to refine a preset.
+    refining: Option<Vec<usize>>,
     pub current_preset: usize,
     error_message: Option<String>,
+    /// Preset read from a bundle file, added to the list on the next frame.
+    imported_preset: Option<Preset>,
+    /// Paused job read from a file, resumed on the next frame.
+    resumed_job: Option<calculate::PausedJob>,
+    /// Paused job that hasn't been written to a file yet. It is kept until it is, so the work
+    /// isn't lost when the save dialog is dismissed or writing fails.
+    unsaved_job: Option<calculate::PausedJob>,
+    show_preset_info: bool,
+    /// Stored presets that couldn't be read. They are left untouched in storage until the user
+    /// exports or discards them.
+    pub storage_recovery: Option<StorageRecovery>,
+    /// Folder with user presets in the `presets/<name>/` layout; new results are saved here.
+    #[cfg(not(target_arch = "wasm32"))]
+    presets_dir: Option<std::path::PathBuf>,
 }
 
 impl GuiState {
-    pub fn default(presets: Vec<Preset>, current_preset: usize) -> GuiState {
-        GuiState {
+    pub fn default(
+        presets: Vec<Preset>,
+        current_preset: usize,
+        storage_recovery: Option<StorageRecovery>,
+    ) -> GuiState {
+        #[allow(unused_mut)]
+        let mut state = GuiState {
             animate: true,
             //fps_text: String::new(),
             presets,
             mode: GuiMode::Transform,
             show_progress_modal: None,
             last_progress: 0.0,
-            process_cancelled: Arc::new(AtomicBool::new(false)),
+            job_control: Arc::default(),
+            job_pauses: false,
             #[cfg(not(target_arch = "wasm32"))]
             last_mouse_pos: None,
             #[cfg(not(target_arch = "wasm32"))]
@@ -73,8 +126,72 @@ impl GuiState {
             //currently_processing: None,
             //current_settings: GenerationSettings::default(),
             configuring_generation: None,
+            refining: None,
             current_preset,
             error_message: None,
+            imported_preset: None,
+            resumed_job: None,
+            unsaved_job: None,
+            show_preset_info: false,
+            storage_recovery,
+            #[cfg(not(target_arch = "wasm32"))]
+            presets_dir: eframe::storage_dir("obamify").map(|dir| dir.join("presets")),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        state.reload_presets_dir();
+        state
+    }
+
+    /// Adds presets from the user presets folder that aren't in the list yet (matched by name).
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_presets_dir(&mut self) {
+        let Some(dir) = &self.presets_dir else {
+            return;
+        };
+        let loaded = match calculate::util::load_presets_dir(dir) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.show_error(format!("failed to read {}: {}", dir.display(), e));
+                return;
+            }
+        };
+        let mut failed = Vec::new();
+        for (path, preset) in loaded {
+            match preset {
+                Ok(preset) => {
+                    if !self
+                        .presets
+                        .iter()
+                        .any(|p| p.inner.name == preset.inner.name)
+                    {
+                        self.presets.push(preset);
+                    }
+                }
+                Err(e) => failed.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        if !failed.is_empty() {
+            self.show_error(format!(
+                "some presets could not be loaded:\n{}",
+                failed.join("\n")
+            ));
+        }
+    }
+
+    /// Writes a finished preset to the user presets folder, renaming it after the directory
+    /// it ended up in so it isn't loaded twice.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_to_presets_dir(&mut self, preset: &mut Preset) {
+        let (Some(dir), Some(settings)) = (&self.presets_dir, &preset.settings) else {
+            return;
+        };
+        match calculate::util::save_result(dir, preset, settings) {
+            Ok(saved) => {
+                if let Some(name) = saved.file_name() {
+                    preset.inner.name = name.to_string_lossy().to_string();
+                }
+            }
+            Err(e) => self.show_error(format!("failed to save preset: {}", e)),
         }
     }
 
@@ -90,7 +207,7 @@ impl GuiState {
         show_icons();
     }
 
-    fn show_error(&mut self, msg: String) {
+    pub fn show_error(&mut self, msg: String) {
         self.error_message = Some(msg);
     }
 
@@ -127,7 +244,31 @@ fn hide_icons() {
 
 impl App for ObamifyApp {
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, "presets", &self.gui.presets);
+        let key = if self.gui.storage_recovery.is_some() {
+            // don't overwrite presets we couldn't read until the user has decided what to do, but
+            // keep this session's presets apart in the meantime
+            storage::RECOVERY_PRESETS_KEY
+        } else {
+            storage.set_string(storage::RECOVERY_PRESETS_KEY, String::new());
+            storage::PRESETS_KEY
+        };
+        // broken presets are kept in the list for this session, but their assignments can't be
+        // stored
+        let valid = self
+            .gui
+            .presets
+            .iter()
+            .filter(|preset| match validate_preset(preset) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::warn!("not storing preset \"{}\": {e}", preset.inner.name);
+                    false
+                }
+            });
+        match storage::encode_presets(valid) {
+            Ok(data) => storage.set_string(key, data),
+            Err(e) => log::error!("failed to encode presets: {e}"),
+        }
     }
     fn update(&mut self, ctx: &egui::Context, frame: &mut Frame) {
         let Some(rs) = frame.wgpu_render_state() else {
@@ -158,6 +299,29 @@ impl App for ObamifyApp {
         #[cfg(target_arch = "wasm32")]
         self.ensure_worker(ctx);
 
+        if let Some(preset) = self.gui.imported_preset.take() {
+            self.gui.presets.push(preset.clone());
+            self.change_sim(device, &rs.queue, preset, self.gui.presets.len() - 1);
+            self.gui.mode = GuiMode::Transform;
+            self.gui.animate = true;
+        }
+
+        if let Some(job) = self.gui.resumed_job.take() {
+            self.gui.show_progress_modal(job.settings.id);
+            self.gui.job_control = Arc::default();
+            self.gui.job_pauses = true;
+            self.resize_textures(device, (job.settings.sidelen, job.settings.sidelen), false);
+
+            #[cfg(target_arch = "wasm32")]
+            self.start_resume_job(job);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.spawn_job(
+                job.settings.id,
+                self.gui.job_control.clone(),
+                move |sink, control| calculate::resume(job, sink, control),
+            );
+        }
+
         // Run GPU pipeline
         if let Some(img) = &self.preview_image {
             // show image
@@ -285,17 +449,27 @@ impl App for ObamifyApp {
                                         self.preview_image = image;
                                     }
                                     ProgressMsg::Cancelled => {
-                                        self.gui.process_cancelled.store(false, Ordering::Relaxed);
+                                        self.gui.job_control = Arc::default();
                                         self.preview_image = None;
 
                                         ui.close();
                                     }
                                     ProgressMsg::UpdateAssignments(assignments) => {
-                                        self.sim.set_assignments(assignments, self.size.0)
+                                        if let Err(e) =
+                                            self.sim.set_assignments(assignments, self.size.0)
+                                        {
+                                            self.gui
+                                                .show_error(format!("invalid assignments: {e}"));
+                                        }
                                     }
                                     ProgressMsg::Progress(_) => todo!(),
                                     ProgressMsg::Done(_) => todo!(),
                                     ProgressMsg::Error(_) => todo!(),
+                                    ProgressMsg::Paused(_) => {
+                                        // drawing jobs can't be paused, so this is from a
+                                        // job that was replaced
+                                        log::debug!("dropping paused message while drawing");
+                                    }
                                 }
                             }
 
@@ -304,6 +478,7 @@ impl App for ObamifyApp {
                                 .on_hover_text("transform mode")
                                 .clicked()
                             {
+                                self.stop_drawing();
                                 self.gui.mode = GuiMode::Transform;
                                 self.change_sim(device, &rs.queue, self.gui.presets[0].clone(), 0);
                             }
@@ -443,6 +618,45 @@ impl App for ObamifyApp {
                                             });
                                         }
 
+                                        ui.separator();
+                                        if ui.button("import preset…").clicked() {
+                                            import_preset(self);
+                                            close_menu = true;
+                                        }
+                                        if ui.button("resume paused job…").clicked() {
+                                            import_paused_job(self);
+                                            close_menu = true;
+                                        }
+                                        #[cfg(not(target_arch = "wasm32"))]
+                                        if let Some(dir) = self.gui.presets_dir.clone() {
+                                            if ui.button("reload presets folder").clicked() {
+                                                self.gui.reload_presets_dir();
+                                            }
+                                            if ui.button("open presets folder").clicked() {
+                                                if let Err(e) = std::fs::create_dir_all(&dir)
+                                                    .map_err(|e| e.to_string())
+                                                    .and_then(|_| {
+                                                        opener::open(&dir)
+                                                            .map_err(|e| e.to_string())
+                                                    })
+                                                {
+                                                    self.gui.show_error(format!(
+                                                        "failed to open {}: {}",
+                                                        dir.display(),
+                                                        e
+                                                    ));
+                                                }
+                                                close_menu = true;
+                                            }
+                                        }
+                                        if ui.button("export current preset…").clicked() {
+                                            export_preset(
+                                                self,
+                                                &self.gui.presets[self.gui.current_preset].clone(),
+                                            );
+                                            close_menu = true;
+                                        }
+
                                         if let Some(idx) = to_remove {
                                             let removed_current = idx == self.gui.current_preset;
                                             self.gui.presets.remove(idx);
@@ -465,6 +679,14 @@ impl App for ObamifyApp {
                                         }
                                     });
 
+                                if ui
+                                    .selectable_label(self.gui.show_preset_info, "ℹ")
+                                    .on_hover_text("preset settings")
+                                    .clicked()
+                                {
+                                    self.gui.show_preset_info = !self.gui.show_preset_info;
+                                }
+
                                 if ui.button("obamify new image").clicked() {
                                     // open file select
                                     prompt_image(
@@ -477,6 +699,7 @@ impl App for ObamifyApp {
                                                 GenerationSettings::default(Uuid::new_v4(), name),
                                                 GuiImageCache::default(),
                                             ));
+                                            app.gui.refining = None;
                                             #[cfg(target_arch = "wasm32")]
                                             hide_icons();
                                         },
@@ -553,6 +776,8 @@ impl App for ObamifyApp {
 
                             let mut change_source = false;
                             let mut change_target = false;
+                            let mut target_error = None;
+                            let refining = self.gui.refining.is_some();
 
                             ui.allocate_ui_with_layout(
                                 egui::vec2(max_w, 0.0),
@@ -564,13 +789,29 @@ impl App for ObamifyApp {
                                     if let Some((source_img, settings, cache)) =
                                         self.gui.configuring_generation.as_mut()
                                     {
-                                        change_source = image_crop_gui(
-                                            "source",
-                                            ui,
-                                            source_img,
-                                            &mut settings.source_crop_scale,
-                                            &mut cache.source_preview,
-                                        );
+                                        let raw_target =
+                                            settings.get_raw_target().unwrap_or_else(|e| {
+                                                // fall back to the built-in target
+                                                target_error = Some(e);
+                                                settings.take_custom_target();
+                                                cache.target_preview = None;
+                                                settings
+                                                    .get_raw_target()
+                                                    .expect("the built-in target decodes")
+                                            });
+                                        // the refined assignments belong to the preset's source
+                                        // as it was stored
+                                        change_source = ui
+                                            .add_enabled_ui(!refining, |ui| {
+                                                image_crop_gui(
+                                                    "source",
+                                                    ui,
+                                                    source_img,
+                                                    &mut settings.source_crop_scale,
+                                                    &mut cache.source_preview,
+                                                )
+                                            })
+                                            .inner;
                                         if is_landscape {
                                             // ./arrow-right.svg
                                             ui.vertical(|ui| {
@@ -580,7 +821,7 @@ impl App for ObamifyApp {
                                                     settings,
                                                     cache,
                                                     source_img,
-                                                    &settings.get_raw_target(),
+                                                    &raw_target,
                                                     0.5,
                                                 );
 
@@ -596,7 +837,7 @@ impl App for ObamifyApp {
                                         change_target = image_crop_gui(
                                             "target",
                                             ui,
-                                            &settings.get_raw_target(),
+                                            &raw_target,
                                             &mut settings.target_crop_scale,
                                             &mut cache.target_preview,
                                         );
@@ -604,6 +845,10 @@ impl App for ObamifyApp {
                                 },
                             );
 
+                            if let Some(e) = target_error {
+                                self.gui.show_error(e.to_string());
+                            }
+
                             if change_source {
                                 prompt_image(
                                     "choose image to obamify",
@@ -629,6 +874,7 @@ impl App for ObamifyApp {
                                         {
                                             settings.set_raw_target(img);
                                             cache.target_preview = None;
+                                            cache.weights_preview = None;
                                         }
                                     },
                                 );
@@ -636,7 +882,7 @@ impl App for ObamifyApp {
 
                             ui.separator();
 
-                            if let Some((_img, settings, _)) =
+                            if let Some((_img, settings, cache)) =
                                 self.gui.configuring_generation.as_mut()
                             {
                                 egui::CollapsingHeader::new("advanced settings")
@@ -646,15 +892,30 @@ impl App for ObamifyApp {
                                             egui::vec2(max_w, 0.0),
                                             egui::Layout::top_down(egui::Align::Min),
                                             |ui| {
-                                                let slider_w = ui.available_width().min(260.0);
-                                                ui.add_sized(
-                                                    [slider_w, 20.0],
-                                                    egui::Slider::new(
-                                                        &mut settings.sidelen,
-                                                        64..=256,
-                                                    )
-                                                    .text("resolution"),
-                                                );
+                                                if refining {
+                                                    // the preset's source is stored at its resolution
+                                                    ui.label(format!(
+                                                        "resolution: {} (kept when refining)",
+                                                        settings.sidelen
+                                                    ));
+                                                } else {
+                                                    let slider_w = ui.available_width().min(260.0);
+                                                    ui.add_sized(
+                                                        [slider_w, 20.0],
+                                                        egui::Slider::new(
+                                                            &mut settings.sidelen,
+                                                            64..=MAX_SIDELEN,
+                                                        )
+                                                        .logarithmic(true)
+                                                        .text("resolution"),
+                                                    );
+                                                    if settings.sidelen > SLOW_SIDELEN {
+                                                        ui.colored_label(
+                                                            Color32::YELLOW,
+                                                            "⚠ high resolutions can take a very long time, especially with the optimal algorithm",
+                                                        );
+                                                    }
+                                                }
 
                                                 let slider_w = ui.available_width().min(260.0);
                                                 ui.add_sized(
@@ -666,56 +927,61 @@ impl App for ObamifyApp {
                                                     .text("proximity importance"),
                                                 );
 
-                                                let mut algorithm = match settings.algorithm {
-                                                    calculate::util::Algorithm::Optimal => {
-                                                        "optimal algorithm"
-                                                    }
-                                                    calculate::util::Algorithm::Genetic => {
-                                                        "fast algorithm"
-                                                    }
-                                                };
-
                                                 egui::ComboBox::from_id_salt("algorithm_select")
-                                                    .selected_text(algorithm)
+                                                    .selected_text(algorithm_label(
+                                                        settings.algorithm,
+                                                    ))
                                                     .show_ui(ui, |ui| {
-                                                        if ui.button("optimal algorithm").clicked()
-                                                        {
-                                                            algorithm = "optimal algorithm";
-                                                            settings.algorithm =
-                                                                calculate::util::Algorithm::Optimal;
-                                                        }
-                                                        if ui.button("fast algorithm").clicked() {
-                                                            algorithm = "fast algorithm";
-                                                            settings.algorithm =
-                                                                calculate::util::Algorithm::Genetic;
+                                                        for solver in solver::SOLVERS.iter().filter(
+                                                            |solver| {
+                                                                !refining || solver.warm_starts()
+                                                            },
+                                                        ) {
+                                                            ui.selectable_value(
+                                                                &mut settings.algorithm,
+                                                                solver.algorithm(),
+                                                                solver.label(),
+                                                            )
+                                                            .on_hover_text(solver.description());
                                                         }
                                                     });
+
+                                                if settings.algorithm == Algorithm::ANNEALING {
+                                                    annealing_settings(ui, &mut settings.annealing);
+                                                }
+
+                                                cost_model_settings(ui, &mut settings.cost_model);
+                                                if settings.has_custom_target() {
+                                                    auto_weights_settings(ui, settings, cache);
+                                                }
+                                                time_budget_settings(ui, &mut settings.time_budget);
                                             },
                                         );
                                     });
                             }
                             ui.separator();
                             ui.horizontal_wrapped(|ui| {
+                                let start_label = if self.gui.refining.is_some() {
+                                    "refine!"
+                                } else {
+                                    "start!"
+                                };
                                 if ui
-                                    .add(egui::Button::new(egui::RichText::new("start!").strong()))
+                                    .add(egui::Button::new(
+                                        egui::RichText::new(start_label).strong(),
+                                    ))
                                     .clicked()
                                 {
-                                    if let Some((img, mut settings, _)) =
+                                    if let Some((img, settings, _)) =
                                         self.gui.configuring_generation.take()
                                     {
                                         self.gui.show_progress_modal(settings.id);
                                         //self.gui.currently_processing = Some(path.clone());
                                         //self.change_sim(device, path.clone(), false);
 
-                                        // adjust for consistency across resolutions
-                                        settings.proximity_importance =
-                                            (settings.proximity_importance as f32
-                                                / (settings.sidelen as f32 / 128.0))
-                                                as i64;
-
-                                        self.gui
-                                            .process_cancelled
-                                            .store(false, std::sync::atomic::Ordering::Relaxed);
+                                        self.gui.job_control = Arc::default();
+                                        self.gui.job_pauses =
+                                            solver::solver(settings.algorithm).pauses();
 
                                         let unprocessed = UnprocessedPreset {
                                             name: settings.name.clone(),
@@ -730,36 +996,49 @@ impl App for ObamifyApp {
                                             false,
                                         );
 
+                                        let refining = self.gui.refining.take();
+
                                         #[cfg(target_arch = "wasm32")]
-                                        {
-                                            self.start_job(unprocessed, settings);
+                                        match refining {
+                                            Some(assignments) => self.start_refine_job(
+                                                Preset {
+                                                    inner: unprocessed,
+                                                    assignments,
+                                                    settings: None,
+                                                },
+                                                settings,
+                                            ),
+                                            None => self.start_job(unprocessed, settings),
                                         }
 
                                         #[cfg(not(target_arch = "wasm32"))]
-                                        {
-                                            std::thread::spawn({
-                                                let tx = self.progress_tx.clone();
-                                                let cancelled = self.gui.process_cancelled.clone();
-                                                move || {
-                                                    let result = calculate::process(
-                                                        unprocessed,
-                                                        settings,
-                                                        &mut tx.clone(),
-                                                        cancelled,
-                                                    );
-                                                    if let Err(err) = result {
-                                                        tx.send(ProgressMsg::Error(
-                                                            err.to_string(),
-                                                        ))
-                                                        .ok();
-                                                    }
-                                                }
-                                            });
-                                        }
+                                        self.spawn_job(
+                                            settings.id,
+                                            self.gui.job_control.clone(),
+                                            move |sink, control| match refining {
+                                                Some(assignments) => calculate::refine(
+                                                    Preset {
+                                                        inner: unprocessed,
+                                                        assignments,
+                                                        settings: None,
+                                                    },
+                                                    settings,
+                                                    sink,
+                                                    control,
+                                                ),
+                                                None => calculate::process(
+                                                    unprocessed,
+                                                    settings,
+                                                    sink,
+                                                    control,
+                                                ),
+                                            },
+                                        );
                                     }
                                 }
                                 if ui.button("cancel").clicked() {
                                     self.gui.configuring_generation = None;
+                                    self.gui.refining = None;
                                     #[cfg(target_arch = "wasm32")]
                                     show_icons();
                                 }
@@ -782,7 +1061,10 @@ impl App for ObamifyApp {
                         ui.set_min_width(ui.available_width().min(400.0));
                         while let Some(msg) = self.get_latest_msg() {
                             match msg {
-                                ProgressMsg::Done(new_preset) => {
+                                #[allow(unused_mut)]
+                                ProgressMsg::Done(mut new_preset) => {
+                                    #[cfg(not(target_arch = "wasm32"))]
+                                    self.gui.save_to_presets_dir(&mut new_preset);
                                     self.preview_image = None;
                                     self.resize_textures(
                                         device,
@@ -790,11 +1072,11 @@ impl App for ObamifyApp {
                                         false,
                                     );
                                     //self.gui.presets = get_presets();
-                                    self.gui.presets.push(new_preset.clone());
+                                    self.gui.presets.push((*new_preset).clone());
                                     self.change_sim(
                                         device,
                                         &rs.queue,
-                                        new_preset,
+                                        *new_preset,
                                         self.gui.presets.len() - 1,
                                     );
                                     self.gui.animate = true;
@@ -829,14 +1111,34 @@ impl App for ObamifyApp {
                                     self.gui.hide_progress_modal();
                                     ui.close();
                                 }
+                                ProgressMsg::Paused(job) => {
+                                    self.preview_image = None;
+                                    self.resize_textures(
+                                        device,
+                                        (DEFAULT_RESOLUTION, DEFAULT_RESOLUTION),
+                                        false,
+                                    );
+                                    self.gui.hide_progress_modal();
+                                    self.gui.unsaved_job = Some(*job);
+                                    save_paused_job(self);
+                                    ui.close();
+                                }
                                 ProgressMsg::UpdateAssignments(assignments) => {
-                                    self.sim.set_assignments(assignments, self.size.0)
+                                    if let Err(e) =
+                                        self.sim.set_assignments(assignments, self.size.0)
+                                    {
+                                        self.gui.show_error(format!("invalid assignments: {e}"));
+                                    }
                                 }
                             }
                         }
 
-                        if self.gui.process_cancelled.load(Ordering::Relaxed) {
+                        if self.gui.job_control.is_cancelled() {
                             ui.label("cancelling...");
+                        } else if self.gui.job_control.is_pause_requested() {
+                            ui.label("pausing...");
+                        } else if self.gui.job_control.is_finish_requested() {
+                            ui.label("finishing...");
                         } else if self.gui.last_progress == 0.0 {
                             ui.label("preparing...");
                         } else {
@@ -847,23 +1149,36 @@ impl App for ObamifyApp {
                         ui.horizontal(|ui| {
                             if ui.button("cancel").clicked() {
                                 #[cfg(target_arch = "wasm32")]
-                                {
-                                    if let Some(w) = &self.worker {
-                                        w.terminate();
-                                    }
-                                    self.worker = None;
-                                    self.preview_image = None;
-                                    self.resize_textures(
-                                        device,
-                                        (DEFAULT_RESOLUTION, DEFAULT_RESOLUTION),
-                                        false,
-                                    );
-                                    self.gui.hide_progress_modal();
-                                    ui.close();
-                                }
-                                self.gui.process_cancelled.store(true, Ordering::Relaxed);
+                                self.cancel_job();
+                                self.gui.job_control.cancel();
                                 self.gui.last_progress = 0.0;
                             }
+                            if ui
+                                .add_enabled(
+                                    !self.gui.job_control.is_finish_requested(),
+                                    egui::Button::new("finish now"),
+                                )
+                                .on_hover_text("stop early and keep the best result so far")
+                                .clicked()
+                            {
+                                #[cfg(target_arch = "wasm32")]
+                                self.finish_job();
+                                self.gui.job_control.finish();
+                            }
+                            if ui
+                                .add_enabled(
+                                    self.gui.job_pauses
+                                        && !self.gui.job_control.is_pause_requested(),
+                                    egui::Button::new("pause"),
+                                )
+                                .on_hover_text("stop and save the job to a file, to resume later")
+                                .on_disabled_hover_text("this algorithm can't be paused")
+                                .clicked()
+                            {
+                                #[cfg(target_arch = "wasm32")]
+                                self.pause_job();
+                                self.gui.job_control.pause();
+                            }
                         })
                     });
                 });
@@ -910,6 +1225,125 @@ impl App for ObamifyApp {
                 },
             );
         }
+        if self.gui.show_preset_info
+            && self.gui.configuring_generation.is_none()
+            && matches!(self.gui.mode, GuiMode::Transform)
+        {
+            let preset = &self.gui.presets[self.gui.current_preset];
+            let mut rerun = false;
+            let mut refine = false;
+            Window::new("preset settings")
+                .open(&mut self.gui.show_preset_info)
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+                .show(ctx, |ui| {
+                    ui.label(egui::RichText::new(&preset.inner.name).strong());
+                    if let Some(settings) = &preset.settings {
+                        preset_settings_grid(ui, settings);
+                    } else {
+                        ui.label("the settings used for this preset weren't recorded.");
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        rerun = ui
+                            .button("re-run with tweaks")
+                            .on_hover_text("open the obamification settings with these values")
+                            .clicked();
+                        refine = ui
+                            .button("refine")
+                            .on_hover_text("keep optimizing this result instead of starting over")
+                            .clicked();
+                    });
+                });
+            if rerun || refine {
+                let configuring = if rerun {
+                    rerun_settings(preset)
+                } else {
+                    refine_settings(preset)
+                };
+                match configuring {
+                    Ok(configuring) => {
+                        self.gui.configuring_generation = Some(configuring);
+                        self.gui.refining = refine.then(|| preset.assignments.clone());
+                        #[cfg(target_arch = "wasm32")]
+                        hide_icons();
+                    }
+                    Err(e) => self.gui.error_message = Some(e.to_string()),
+                }
+            }
+        }
+        if let Some(recovery) = &self
+            .gui
+            .storage_recovery
+            .as_ref()
+            .filter(|recovery| !recovery.dismissed)
+        {
+            let mut export = false;
+            let mut discard = false;
+            let mut dismiss = false;
+            Window::new("couldn't load your presets")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(recovery.error.to_string());
+                    ui.label(
+                        "the built-in presets are shown for now. your saved presets are kept \
+                         until you discard them, so you can export them or try again with \
+                         another version. presets you make in the meantime are saved \
+                         separately and kept either way.",
+                    );
+                    ui.horizontal_wrapped(|ui| {
+                        export = ui.button("export raw data…").clicked();
+                        discard = ui.button("discard saved presets").clicked();
+                        dismiss = ui.button("keep for now").clicked();
+                    });
+                });
+            if export {
+                let raw = recovery.raw.clone().into_bytes();
+                save_file(self, "export saved presets", "presets.ron".to_owned(), raw);
+            } else if discard {
+                self.gui.storage_recovery = None;
+            } else if dismiss {
+                if let Some(recovery) = &mut self.gui.storage_recovery {
+                    recovery.dismissed = true;
+                }
+            }
+        }
+        if let Some(job) = self
+            .gui
+            .unsaved_job
+            .as_ref()
+            .filter(|_| self.gui.show_progress_modal.is_none())
+        {
+            let mut save = false;
+            let mut resume = false;
+            let mut discard = false;
+            Window::new("paused job not saved")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "\"{}\" was paused but hasn't been saved to a file, so its progress is \
+                         lost if you discard it.",
+                        job.source.name
+                    ));
+                    ui.horizontal_wrapped(|ui| {
+                        save = ui.button("save…").clicked();
+                        resume = ui.button("resume").clicked();
+                        discard = ui.button("discard").clicked();
+                    });
+                });
+            if save {
+                save_paused_job(self);
+            } else if resume {
+                self.gui.resumed_job = self.gui.unsaved_job.take();
+            } else if discard {
+                self.gui.unsaved_job = None;
+            }
+        }
         if let Some(err) = &self.gui.error_message {
             let mut close = false;
             Window::new("error")
@@ -1174,6 +1608,416 @@ fn prompt_image(
     }
 }
 
+fn algorithm_label(algorithm: calculate::util::Algorithm) -> &'static str {
+    solver::solver(algorithm).label()
+}
+
+fn annealing_settings(ui: &mut egui::Ui, annealing: &mut AnnealingSettings) {
+    let slider_w = ui.available_width().min(260.0);
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut annealing.initial_temperature, 0.001..=10.0)
+            .logarithmic(true)
+            .text("initial temperature"),
+    );
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut annealing.cooling_rate, 0.5..=0.999).text("cooling rate"),
+    );
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut annealing.final_temperature, 0.0001..=1.0)
+            .logarithmic(true)
+            .text("final temperature"),
+    );
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut annealing.swaps_per_pixel, 1..=512)
+            .logarithmic(true)
+            .text("swaps per pixel"),
+    );
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut annealing.min_swaps_per_generation, 1..=1000)
+            .logarithmic(true)
+            .text("stop below swaps"),
+    );
+}
+
+fn cost_model_settings(ui: &mut egui::Ui, model: &mut CostModel) {
+    egui::ComboBox::from_id_salt("color_space_select")
+        .selected_text(model.color_space.label())
+        .show_ui(ui, |ui| {
+            for space in ColorSpace::ALL {
+                ui.selectable_value(&mut model.color_space, space, space.label());
+            }
+        })
+        .response
+        .on_hover_text("how color differences are measured");
+    let slider_w = ui.available_width().min(260.0);
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut model.spatial_exponent, CostModel::SPATIAL_EXPONENTS)
+            .text("spatial exponent"),
+    )
+    .on_hover_text("higher values punish long moves harder compared to short ones");
+}
+
+/// Side length the automatic weights are previewed at.
+const WEIGHTS_PREVIEW_SIDE: u32 = 128;
+
+/// Sliders blending the cues a custom target's weights are made from, and a preview of the
+/// weights.
+fn auto_weights_settings(
+    ui: &mut egui::Ui,
+    settings: &mut GenerationSettings,
+    cache: &mut GuiImageCache,
+) {
+    ui.label("importance of target pixels:");
+    let slider_w = ui.available_width().min(260.0);
+    let weights = &mut settings.auto_weights;
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut weights.edges, 0.0..=1.0).text("edges"),
+    );
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut weights.contrast, 0.0..=1.0).text("contrast"),
+    );
+    ui.add_sized(
+        [slider_w, 20.0],
+        egui::Slider::new(&mut weights.center, 0.0..=1.0).text("center"),
+    );
+
+    let key = (settings.target_crop_scale, settings.auto_weights);
+    let tex = match &cache.weights_preview {
+        Some((made_for, tex)) if *made_for == key => tex.clone(),
+        _ => {
+            let Ok(raw_target) = settings.get_raw_target() else {
+                return;
+            };
+            let target = settings
+                .target_crop_scale
+                .apply(&raw_target, WEIGHTS_PREVIEW_SIDE);
+            let gray = settings
+                .auto_weights
+                .weights(&target)
+                .into_iter()
+                .map(|w| w as u8)
+                .collect::<Vec<_>>();
+            let side = WEIGHTS_PREVIEW_SIDE as usize;
+            let tex = ui.ctx().load_texture(
+                "weights preview",
+                egui::ColorImage::from_gray([side, side], &gray),
+                egui::TextureOptions::LINEAR,
+            );
+            cache.weights_preview = Some((key, tex.clone()));
+            tex
+        }
+    };
+    ui.add(egui::Image::from_texture(&tex))
+        .on_hover_text("brighter pixels of the target are matched more closely");
+}
+
+/// Seconds a new time limit starts at.
+const DEFAULT_TIME_BUDGET: u32 = 60;
+
+fn time_budget_settings(ui: &mut egui::Ui, time_budget: &mut Option<u32>) {
+    ui.horizontal(|ui| {
+        let mut limited = time_budget.is_some();
+        ui.checkbox(&mut limited, "time limit")
+            .on_hover_text("stop after this long and keep the best result so far");
+        if limited {
+            let secs = time_budget.get_or_insert(DEFAULT_TIME_BUDGET);
+            ui.add(egui::Slider::new(secs, 5..=600).suffix(" s"));
+        } else {
+            *time_budget = None;
+        }
+    });
+}
+
+fn preset_settings_grid(ui: &mut egui::Ui, settings: &GenerationSettings) {
+    let crop_text =
+        |crop: &CropScale| format!("x {:.2}, y {:.2}, zoom {:.2}", crop.x, crop.y, crop.scale);
+    egui::Grid::new("preset_settings")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("algorithm");
+            ui.label(algorithm_label(settings.algorithm));
+            ui.end_row();
+            ui.label("resolution");
+            ui.label(format!("{0}×{0}", settings.sidelen));
+            ui.end_row();
+            ui.label("proximity importance");
+            ui.label(settings.proximity_importance.to_string());
+            ui.end_row();
+            if settings.algorithm == Algorithm::ANNEALING {
+                let annealing = &settings.annealing;
+                ui.label("annealing");
+                ui.label(format!(
+                    "temperature {} → {}, cooling {}, {} swaps per pixel, stop below {} swaps",
+                    annealing.initial_temperature,
+                    annealing.final_temperature,
+                    annealing.cooling_rate,
+                    annealing.swaps_per_pixel,
+                    annealing.min_swaps_per_generation
+                ));
+                ui.end_row();
+            }
+            ui.label("color difference");
+            ui.label(settings.cost_model.color_space.label());
+            ui.end_row();
+            ui.label("spatial exponent");
+            ui.label(settings.cost_model.spatial_exponent.to_string());
+            ui.end_row();
+            if let Some(secs) = settings.time_budget {
+                ui.label("time limit");
+                ui.label(format!("{secs} s"));
+                ui.end_row();
+            }
+            ui.label("source crop");
+            ui.label(crop_text(&settings.source_crop_scale));
+            ui.end_row();
+            ui.label("target");
+            ui.label(if settings.has_custom_target() {
+                "custom image"
+            } else {
+                "obama"
+            });
+            ui.end_row();
+            if settings.has_custom_target() {
+                let weights = &settings.auto_weights;
+                ui.label("target importance");
+                ui.label(if weights.is_uniform() {
+                    "uniform".to_owned()
+                } else {
+                    format!(
+                        "edges {:.2}, contrast {:.2}, center {:.2}",
+                        weights.edges, weights.contrast, weights.center
+                    )
+                });
+                ui.end_row();
+            }
+            ui.label("target crop");
+            ui.label(crop_text(&settings.target_crop_scale));
+            ui.end_row();
+        });
+}
+
+/// Source image and settings for generating a preset again. The stored source has already been
+/// cropped and scaled, so its crop is reset instead of being applied a second time. Fails if the
+/// source doesn't match its dimensions, which an imported or migrated preset may not.
+fn rerun_settings(
+    preset: &Preset,
+) -> Result<(SourceImg, GenerationSettings, GuiImageCache), CalculateError> {
+    let source = calculate::util::source_image(&preset.inner)?;
+    let mut settings = preset
+        .settings
+        .clone()
+        .unwrap_or_else(|| GenerationSettings::default(Uuid::new_v4(), String::new()));
+    settings.id = Uuid::new_v4();
+    settings.name = preset.inner.name.clone();
+    settings.source_crop_scale = CropScale::identity();
+    Ok((source, settings, GuiImageCache::default()))
+}
+
+/// Like [`rerun_settings`], but for refining the preset's result: the resolution is the
+/// preset's, and the algorithm one that can continue from it. Fails if the preset's assignments
+/// don't fit its source either.
+fn refine_settings(
+    preset: &Preset,
+) -> Result<(SourceImg, GenerationSettings, GuiImageCache), CalculateError> {
+    validate_preset(preset)?;
+    let (source, mut settings, cache) = rerun_settings(preset)?;
+    settings.sidelen = preset.inner.width;
+    if !solver::solver(settings.algorithm).warm_starts() {
+        settings.algorithm = Algorithm::GENETIC;
+    }
+    Ok((source, settings, cache))
+}
+
+fn import_preset(app: &mut ObamifyApp) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+        let app_ptr: *mut ObamifyApp = app;
+
+        spawn_local(async move {
+            if let Some(handle) = rfd::AsyncFileDialog::new()
+                .set_title("import preset")
+                .add_filter("obamify preset", &[bundle::BUNDLE_EXTENSION])
+                .pick_file()
+                .await
+            {
+                let data = handle.read().await;
+                let result = bundle::import_preset(&data);
+                unsafe {
+                    if let Some(app) = app_ptr.as_mut() {
+                        match result {
+                            Ok(preset) => app.gui.imported_preset = Some(preset),
+                            Err(e) => app
+                                .gui
+                                .show_error(format!("failed to import preset: {}", e)),
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(file) = rfd::FileDialog::new()
+            .set_title("import preset")
+            .add_filter("obamify preset", &[bundle::BUNDLE_EXTENSION])
+            .pick_file()
+        {
+            match std::fs::read(file)
+                .map_err(|e| e.into())
+                .and_then(|data| bundle::import_preset(&data))
+            {
+                Ok(preset) => app.gui.imported_preset = Some(preset),
+                Err(e) => app
+                    .gui
+                    .show_error(format!("failed to import preset: {}", e)),
+            }
+        }
+    }
+}
+
+fn import_paused_job(app: &mut ObamifyApp) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+        let app_ptr: *mut ObamifyApp = app;
+
+        spawn_local(async move {
+            if let Some(handle) = rfd::AsyncFileDialog::new()
+                .set_title("resume paused job")
+                .add_filter("paused obamify job", &[paused::JOB_EXTENSION])
+                .pick_file()
+                .await
+            {
+                let data = handle.read().await;
+                let result = paused::import_job(&data);
+                unsafe {
+                    if let Some(app) = app_ptr.as_mut() {
+                        match result {
+                            Ok(job) => app.gui.resumed_job = Some(job),
+                            Err(e) => app
+                                .gui
+                                .show_error(format!("failed to load paused job: {}", e)),
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(file) = rfd::FileDialog::new()
+            .set_title("resume paused job")
+            .add_filter("paused obamify job", &[paused::JOB_EXTENSION])
+            .pick_file()
+        {
+            match std::fs::read(file)
+                .map_err(|e| e.into())
+                .and_then(|data| paused::import_job(&data))
+            {
+                Ok(job) => app.gui.resumed_job = Some(job),
+                Err(e) => app
+                    .gui
+                    .show_error(format!("failed to load paused job: {}", e)),
+            }
+        }
+    }
+}
+
+/// Asks where to save the unsaved paused job, and forgets it once it has been written.
+fn save_paused_job(app: &mut ObamifyApp) {
+    let Some(job) = &app.gui.unsaved_job else {
+        return;
+    };
+    let file_name = format!("{}.{}", job.source.name, paused::JOB_EXTENSION);
+    match paused::export_job(job) {
+        Ok(data) => save_file_then(app, "save paused job", file_name, data, |app| {
+            app.gui.unsaved_job = None;
+        }),
+        Err(e) => app
+            .gui
+            .show_error(format!("failed to save paused job: {}", e)),
+    }
+}
+
+fn export_preset(app: &mut ObamifyApp, preset: &Preset) {
+    match bundle::export_preset(preset) {
+        Ok(data) => save_file(
+            app,
+            "export preset",
+            format!("{}.{}", preset.inner.name, bundle::BUNDLE_EXTENSION),
+            data,
+        ),
+        Err(e) => app
+            .gui
+            .show_error(format!("failed to export preset: {}", e)),
+    }
+}
+
+/// Asks where to save `data` and writes it there, showing an error if that fails.
+fn save_file(app: &mut ObamifyApp, title: &'static str, file_name: String, data: Vec<u8>) {
+    save_file_then(app, title, file_name, data, |_| {});
+}
+
+/// [`save_file`], calling `on_saved` once the data has been written.
+fn save_file_then(
+    app: &mut ObamifyApp,
+    title: &'static str,
+    file_name: String,
+    data: Vec<u8>,
+    on_saved: fn(&mut ObamifyApp),
+) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen_futures::spawn_local;
+        let app_ptr: *mut ObamifyApp = app;
+
+        spawn_local(async move {
+            if let Some(handle) = rfd::AsyncFileDialog::new()
+                .set_title(title)
+                .set_file_name(&file_name)
+                .save_file()
+                .await
+            {
+                let result = handle.write(&data).await;
+                unsafe {
+                    if let Some(app) = app_ptr.as_mut() {
+                        match result {
+                            Ok(()) => on_saved(app),
+                            Err(e) => app.gui.show_error(format!("failed to save file: {}", e)),
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(file) = rfd::FileDialog::new()
+            .set_title(title)
+            .set_file_name(&file_name)
+            .save_file()
+        {
+            match std::fs::write(file, data) {
+                Ok(()) => on_saved(app),
+                Err(e) => app.gui.show_error(format!("failed to save file: {}", e)),
+            }
+        }
+    }
+}
+
 fn ensure_reasonable_size(img: SourceImg) -> SourceImg {
     let max_side = 512;
     let (w, h) = img.dimensions();
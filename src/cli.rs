@@ -0,0 +1,364 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::core::calculate::cost::{ColorSpace, CostModel, MAX_SIDELEN};
+use crate::core::calculate::importance::AutoWeights;
+use crate::core::calculate::{
+    self, ProgressMsg, solver,
+    util::{Algorithm, AnnealingSettings, CropScale, GenerationSettings, JobControl, ProgressSink},
+};
+use crate::core::paused;
+use crate::core::preset::{Preset, UnprocessedPreset};
+
+const USAGE: &str = "\
+usage: obamify generate <source image> [options]
+       obamify resume <paused job file> [--out <dir>] [--threads <n>] [--quiet]
+
+options:
+  --target <image>          custom target image (default: obama)
+  --out <dir>               directory to write the preset to (default: ./presets)
+  --name <name>             preset name (default: source file name)
+  --sidelen <n>             resolution of the transformation, up to 1024 (default: 128)
+  --proximity <n>           proximity importance (default: 13)
+  --algorithm <name>        one of the algorithms below (default: genetic)
+  --source-crop <x,y,zoom>  crop of the source image (default: 0,0,1)
+  --target-crop <x,y,zoom>  crop of the target image (default: 0,0,1)
+  --importance <e,c,m>      custom target: how much edges, contrast and the middle count
+                            towards its pixel weights; 0,0,0 is uniform (default: 0.4,0.35,0.25)
+  --temperature <t>         annealing: initial temperature (default: 0.5)
+  --cooling <rate>          annealing: cooling rate per generation (default: 0.95)
+  --final-temperature <t>   annealing: temperature where it may stop (default: 0.001)
+  --swaps-per-pixel <n>     annealing: swaps per generation, per pixel (default: 128)
+  --min-swaps <n>           annealing: stop below this many swaps (default: 10)
+  --color-space <name>      rgb, oklab, cielab or luminance (default: rgb)
+  --spatial-exponent <n>    power of the distance cost, 1 to 3 (default: 2)
+  --time-budget <seconds>   stop early with the best result so far (default: no limit)
+  --threads <n>             most threads to use (default: one per core)
+  --quiet                   don't print progress
+";
+
+/// The usage text, followed by the available algorithms.
+pub fn usage() -> String {
+    let mut usage = format!("{USAGE}\nalgorithms:\n");
+    for solver in solver::SOLVERS {
+        usage += &format!("  {:<24}  {}\n", solver.name(), solver.description());
+    }
+    usage
+}
+
+/// Runs the headless command line mode if the arguments ask for it.
+///
+/// Returns `None` when no subcommand was given, in which case the GUI should start.
+pub fn run_from_args() -> Option<Result<(), String>> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (command, rest) = args.split_first()?;
+    Some(match command.as_str() {
+        "generate" => parse_generate(rest).and_then(generate),
+        "resume" => parse_resume(rest).and_then(resume),
+        "help" | "--help" | "-h" => {
+            print!("{}", usage());
+            Ok(())
+        }
+        other => Err(format!("unknown command `{other}`\n\n{}", usage())),
+    })
+}
+
+struct GenerateArgs {
+    source: PathBuf,
+    target: Option<PathBuf>,
+    out: PathBuf,
+    name: Option<String>,
+    sidelen: u32,
+    proximity_importance: i64,
+    algorithm: Algorithm,
+    source_crop_scale: CropScale,
+    target_crop_scale: CropScale,
+    annealing: AnnealingSettings,
+    cost_model: CostModel,
+    auto_weights: AutoWeights,
+    time_budget: Option<u32>,
+    threads: usize,
+    quiet: bool,
+}
+
+fn parse_generate(args: &[String]) -> Result<GenerateArgs, String> {
+    let defaults = GenerationSettings::default(Uuid::nil(), String::new());
+    let mut parsed = GenerateArgs {
+        source: PathBuf::new(),
+        target: None,
+        out: PathBuf::from("./presets"),
+        name: None,
+        sidelen: defaults.sidelen,
+        proximity_importance: defaults.proximity_importance,
+        algorithm: defaults.algorithm,
+        source_crop_scale: defaults.source_crop_scale,
+        target_crop_scale: defaults.target_crop_scale,
+        annealing: defaults.annealing,
+        cost_model: defaults.cost_model,
+        auto_weights: defaults.auto_weights,
+        time_budget: defaults.time_budget,
+        threads: 0,
+        quiet: false,
+    };
+    let mut source = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{arg}`"))
+        };
+        match arg.as_str() {
+            "--target" => parsed.target = Some(PathBuf::from(value()?)),
+            "--out" => parsed.out = PathBuf::from(value()?),
+            "--name" => parsed.name = Some(value()?.clone()),
+            "--sidelen" => parsed.sidelen = parse_number(arg, value()?)?,
+            "--proximity" => parsed.proximity_importance = parse_number(arg, value()?)?,
+            "--algorithm" => {
+                parsed.algorithm = match value()?.to_lowercase().as_str() {
+                    // the name the app shows for the genetic solver
+                    "fast" => Algorithm::GENETIC,
+                    name => solver::find_solver(name)
+                        .ok_or_else(|| format!("unknown algorithm `{name}`"))?
+                        .algorithm(),
+                }
+            }
+            "--source-crop" => parsed.source_crop_scale = parse_crop_scale(arg, value()?)?,
+            "--target-crop" => parsed.target_crop_scale = parse_crop_scale(arg, value()?)?,
+            "--importance" => parsed.auto_weights = parse_auto_weights(arg, value()?)?,
+            "--temperature" => parsed.annealing.initial_temperature = parse_number(arg, value()?)?,
+            "--cooling" => parsed.annealing.cooling_rate = parse_number(arg, value()?)?,
+            "--final-temperature" => {
+                parsed.annealing.final_temperature = parse_number(arg, value()?)?
+            }
+            "--swaps-per-pixel" => parsed.annealing.swaps_per_pixel = parse_number(arg, value()?)?,
+            "--min-swaps" => {
+                parsed.annealing.min_swaps_per_generation = parse_number(arg, value()?)?
+            }
+            "--color-space" => {
+                let name = value()?.to_lowercase();
+                parsed.cost_model.color_space = ColorSpace::from_name(&name)
+                    .ok_or_else(|| format!("unknown color space `{name}`"))?;
+            }
+            "--spatial-exponent" => {
+                parsed.cost_model.spatial_exponent = parse_number(arg, value()?)?
+            }
+            "--time-budget" => parsed.time_budget = Some(parse_number(arg, value()?)?),
+            "--threads" => parsed.threads = parse_number(arg, value()?)?,
+            "--quiet" | "-q" => parsed.quiet = true,
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{flag}`")),
+            path if source.is_none() => source = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument `{extra}`")),
+        }
+    }
+
+    parsed.source = source.ok_or_else(|| format!("missing source image\n\n{}", usage()))?;
+    if parsed.sidelen == 0 || parsed.sidelen > MAX_SIDELEN {
+        return Err(format!("`--sidelen` must be between 1 and {MAX_SIDELEN}"));
+    }
+    Ok(parsed)
+}
+
+struct ResumeArgs {
+    job: PathBuf,
+    out: PathBuf,
+    threads: usize,
+    quiet: bool,
+}
+
+fn parse_resume(args: &[String]) -> Result<ResumeArgs, String> {
+    let mut parsed = ResumeArgs {
+        job: PathBuf::new(),
+        out: PathBuf::from("./presets"),
+        threads: 0,
+        quiet: false,
+    };
+    let mut job = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{arg}`"))
+        };
+        match arg.as_str() {
+            "--out" => parsed.out = PathBuf::from(value()?),
+            "--threads" => parsed.threads = parse_number(arg, value()?)?,
+            "--quiet" | "-q" => parsed.quiet = true,
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{flag}`")),
+            path if job.is_none() => job = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument `{extra}`")),
+        }
+    }
+
+    parsed.job = job.ok_or_else(|| format!("missing paused job file\n\n{}", usage()))?;
+    Ok(parsed)
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{arg}`"))
+}
+
+fn parse_crop_scale(arg: &str, value: &str) -> Result<CropScale, String> {
+    let parts = value
+        .split(',')
+        .map(|p| parse_number::<f32>(arg, p.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match parts[..] {
+        [x, y, scale] if scale >= 1.0 => Ok(CropScale { x, y, scale }),
+        [_, _, _] => Err(format!("zoom in `{arg}` must be at least 1")),
+        _ => Err(format!("`{arg}` expects `x,y,zoom`, got `{value}`")),
+    }
+}
+
+fn parse_auto_weights(arg: &str, value: &str) -> Result<AutoWeights, String> {
+    let parts = value
+        .split(',')
+        .map(|p| parse_number::<f32>(arg, p.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match parts[..] {
+        [edges, contrast, center] if edges >= 0.0 && contrast >= 0.0 && center >= 0.0 => {
+            Ok(AutoWeights {
+                edges,
+                contrast,
+                center,
+            })
+        }
+        [_, _, _] => Err(format!("amounts in `{arg}` can't be negative")),
+        _ => Err(format!(
+            "`{arg}` expects `edges,contrast,center`, got `{value}`"
+        )),
+    }
+}
+
+fn generate(args: GenerateArgs) -> Result<(), String> {
+    let source = image::open(&args.source)
+        .map_err(|e| format!("failed to load {}: {e}", args.source.display()))?
+        .to_rgb8();
+    let name = args.name.unwrap_or_else(|| {
+        args.source
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "untitled".to_owned())
+    });
+
+    let mut settings = GenerationSettings::default(Uuid::new_v4(), name.clone());
+    settings.sidelen = args.sidelen;
+    settings.proximity_importance = args.proximity_importance;
+    settings.algorithm = args.algorithm;
+    settings.source_crop_scale = args.source_crop_scale;
+    settings.target_crop_scale = args.target_crop_scale;
+    settings.annealing = args.annealing;
+    settings.cost_model = args.cost_model;
+    settings.auto_weights = args.auto_weights;
+    settings.time_budget = args.time_budget;
+    if let Some(target) = &args.target {
+        let target = image::open(target)
+            .map_err(|e| format!("failed to load {}: {e}", target.display()))?
+            .to_rgb8();
+        settings.set_raw_target(target);
+    }
+
+    let unprocessed = UnprocessedPreset {
+        name,
+        width: source.width(),
+        height: source.height(),
+        source_img: source.into_raw(),
+    };
+
+    calculate::util::set_thread_limit(args.threads);
+    let mut sink = TerminalSink::new(args.quiet);
+    let result = calculate::process(
+        unprocessed,
+        settings,
+        &mut sink,
+        Arc::new(JobControl::default()),
+    );
+    save_output(sink, result, &args.out)
+}
+
+fn resume(args: ResumeArgs) -> Result<(), String> {
+    let job = std::fs::read(&args.job)
+        .map_err(|e| e.to_string())
+        .and_then(|data| paused::import_job(&data).map_err(|e| e.to_string()))
+        .map_err(|e| format!("failed to load {}: {e}", args.job.display()))?;
+
+    calculate::util::set_thread_limit(args.threads);
+    let mut sink = TerminalSink::new(args.quiet);
+    let result = calculate::resume(job, &mut sink, Arc::new(JobControl::default()));
+    save_output(sink, result, &args.out)
+}
+
+/// Writes the preset a finished job sent to `out` and prints where it went.
+fn save_output(
+    mut sink: TerminalSink,
+    result: Result<(), calculate::CalculateError>,
+    out: &std::path::Path,
+) -> Result<(), String> {
+    sink.finish_line();
+    result.map_err(|e| e.to_string())?;
+
+    match sink.result {
+        Some(Ok(preset)) => {
+            let settings = preset
+                .settings
+                .clone()
+                .ok_or("the finished preset has no settings")?;
+            let dir = calculate::util::save_result(out, &preset, &settings)
+                .map_err(|e| format!("failed to save preset: {e}"))?;
+            println!("{}", dir.display());
+            Ok(())
+        }
+        Some(Err(e)) => Err(e),
+        None => Err("generation stopped without a result".to_owned()),
+    }
+}
+
+/// Progress sink that draws a progress line on stderr and keeps the final result.
+struct TerminalSink {
+    quiet: bool,
+    last_percent: Option<u32>,
+    result: Option<Result<Preset, String>>,
+}
+
+impl TerminalSink {
+    fn new(quiet: bool) -> Self {
+        Self {
+            quiet,
+            last_percent: None,
+            result: None,
+        }
+    }
+
+    fn finish_line(&mut self) {
+        if self.last_percent.take().is_some() {
+            eprintln!();
+        }
+    }
+}
+
+impl ProgressSink for TerminalSink {
+    fn send(&mut self, msg: ProgressMsg) {
+        match msg {
+            ProgressMsg::Progress(p) => {
+                let percent = (p.clamp(0.0, 1.0) * 100.0) as u32;
+                if !self.quiet && self.last_percent != Some(percent) {
+                    self.last_percent = Some(percent);
+                    eprint!("\rprocessing... {percent:3}%");
+                    std::io::stderr().flush().ok();
+                }
+            }
+            ProgressMsg::Done(preset) => self.result = Some(Ok(*preset)),
+            ProgressMsg::Error(e) => self.result = Some(Err(e.to_string())),
+            ProgressMsg::Cancelled => self.result = Some(Err("cancelled".to_owned())),
+            // nothing pauses a job from the command line
+            ProgressMsg::Paused(_) => self.result = Some(Err("paused".to_owned())),
+            ProgressMsg::UpdatePreview { .. } | ProgressMsg::UpdateAssignments(_) => {}
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! The image transformation itself, without any of the graphics stack: assignment solvers,
+//! generation settings, presets and the morph simulation. Builds with `default-features = false`.
+
+pub mod bundle;
+pub mod calculate;
+pub mod morph_sim;
+pub mod paused;
+pub mod permutation;
+pub mod preset;
+pub mod storage;
+pub mod validate;
+
+pub use calculate::solver::{AssignmentSolver, SOLVERS};
+pub use calculate::util::{
+    Algorithm, AnnealingSettings, CropScale, GenerationSettings, JobControl, ProgressSink,
+};
+pub use calculate::{PausedJob, ProgressMsg, process_async, refine_async, resume_async};
+#[cfg(not(target_arch = "wasm32"))]
+pub use calculate::{process, refine, resume};
+pub use morph_sim::Sim;
+pub use preset::{Preset, UnprocessedPreset};
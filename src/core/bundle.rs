@@ -0,0 +1,166 @@
+//! Single-file preset bundles, for sharing a finished preset between machines or with the web
+//! version.
+//!
+//! Layout, all integers little endian:
+//! - the magic bytes `OBAMIFY\0`
+//! - the format version as a `u32`
+//! - four sections, each a `u32` byte length followed by the data:
+//!   1. a JSON header with the name, dimensions and generation settings
+//!   2. the source image as PNG
+//!   3. the assignments in the [`permutation`] encoding (version 1: one `u32` per pixel)
+//!   4. the custom target image as PNG, or nothing if the built-in target was used
+//!
+//! The header is JSON so settings added later can be read from older bundles with their defaults.
+
+use std::error::Error;
+use std::io::Cursor;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::calculate::util::{GenerationSettings, SourceImg};
+use crate::core::permutation;
+use crate::core::preset::{Preset, UnprocessedPreset};
+use crate::core::validate::validate_preset;
+
+/// File extension used for bundles, without the dot.
+pub const BUNDLE_EXTENSION: &str = "obamify";
+/// Version written by [`export_preset`]. Bumped whenever the layout changes.
+pub const BUNDLE_VERSION: u32 = 2;
+
+const MAGIC: &[u8; 8] = b"OBAMIFY\0";
+
+#[derive(Serialize, Deserialize)]
+struct BundleHeader {
+    name: String,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    settings: Option<GenerationSettings>,
+}
+
+/// Packs a preset, and the settings it was generated with if known, into a bundle.
+pub fn export_preset(preset: &Preset) -> Result<Vec<u8>, Box<dyn Error>> {
+    validate_preset(preset)?;
+    let mut settings = preset.settings.clone();
+    let custom_target = settings.as_mut().and_then(|s| s.take_custom_target());
+    let header = BundleHeader {
+        name: preset.inner.name.clone(),
+        width: preset.inner.width,
+        height: preset.inner.height,
+        settings,
+    };
+
+    let source: SourceImg = image::ImageBuffer::from_vec(
+        preset.inner.width,
+        preset.inner.height,
+        preset.inner.source_img.clone(),
+    )
+    .ok_or("source image does not match its dimensions")?;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&BUNDLE_VERSION.to_le_bytes());
+    write_section(&mut out, &serde_json::to_vec(&header)?)?;
+    write_section(&mut out, &encode_png(&source)?)?;
+    write_section(&mut out, &permutation::encode(&preset.assignments))?;
+    match custom_target {
+        Some(target) => write_section(&mut out, &encode_png(&target)?)?,
+        None => write_section(&mut out, &[])?,
+    }
+    Ok(out)
+}
+
+/// Reads a bundle written by [`export_preset`].
+pub fn import_preset(data: &[u8]) -> Result<Preset, Box<dyn Error>> {
+    let data = data
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or("not an obamify preset file")?;
+    let mut reader = Reader { data };
+    let version = reader.u32()?;
+    if !(1..=BUNDLE_VERSION).contains(&version) {
+        return Err(format!(
+            "unsupported preset file version {version} (this version reads up to {BUNDLE_VERSION})"
+        )
+        .into());
+    }
+
+    let header: BundleHeader = serde_json::from_slice(reader.section()?)?;
+    let source =
+        image::load_from_memory_with_format(reader.section()?, image::ImageFormat::Png)?.to_rgb8();
+    if source.dimensions() != (header.width, header.height) {
+        return Err("source image does not match the header dimensions".into());
+    }
+
+    let assignments = reader.section()?;
+    let assignments = if version == 1 {
+        assignments
+            .chunks(4)
+            .map(|chunk| {
+                let bytes = <[u8; 4]>::try_from(chunk).map_err(|_| "truncated assignments")?;
+                Ok(u32::from_le_bytes(bytes) as usize)
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?
+    } else {
+        permutation::decode(assignments)?
+    };
+
+    let mut settings = header.settings;
+    let target = reader.section()?;
+    if !target.is_empty() {
+        let target =
+            image::load_from_memory_with_format(target, image::ImageFormat::Png)?.to_rgb8();
+        if let Some(settings) = &mut settings {
+            settings.set_raw_target(target);
+        }
+    }
+
+    let preset = Preset {
+        inner: UnprocessedPreset {
+            name: header.name,
+            width: header.width,
+            height: header.height,
+            source_img: source.into_raw(),
+        },
+        assignments,
+        settings,
+    };
+    validate_preset(&preset)?;
+    Ok(preset)
+}
+
+pub(crate) fn encode_png(img: &SourceImg) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(png)
+}
+
+pub(crate) fn write_section(out: &mut Vec<u8>, section: &[u8]) -> Result<(), Box<dyn Error>> {
+    let len = u32::try_from(section.len()).map_err(|_| "bundle section too large")?;
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(section);
+    Ok(())
+}
+
+pub(crate) struct Reader<'a> {
+    pub data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        if self.data.len() < len {
+            return Err("preset file is truncated".into());
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    pub fn section(&mut self) -> Result<&'a [u8], Box<dyn Error>> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
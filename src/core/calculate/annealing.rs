@@ -0,0 +1,158 @@
+use crate::core::calculate::CalculateError;
+use crate::core::calculate::genetic::{self, Grid};
+use crate::core::calculate::solver::{
+    AssignmentSolver, SolveFuture, SolverContext, SolverInput, SolverState,
+};
+use crate::core::calculate::util::Algorithm;
+use crate::core::validate::validate_assignments;
+
+/// The genetic solver with simulated annealing: swaps that make things worse are sometimes
+/// kept while the temperature is high, which lets it climb out of local minima. Configured by
+/// [`super::util::AnnealingSettings`]; the best assignment seen is the result.
+pub(crate) struct Annealing;
+
+impl AssignmentSolver for Annealing {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::ANNEALING
+    }
+
+    fn name(&self) -> &'static str {
+        "annealing"
+    }
+
+    fn label(&self) -> &'static str {
+        "simulated annealing"
+    }
+
+    fn description(&self) -> &'static str {
+        "like the fast algorithm, but escapes local minima; slower, with tunable parameters"
+    }
+
+    fn warm_starts(&self) -> bool {
+        true
+    }
+
+    fn pauses(&self) -> bool {
+        true
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+const SEED: u64 = 12345;
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    let settings = &input.settings;
+    let params = settings.annealing;
+    if !(params.cooling_rate > 0.0 && params.cooling_rate < 1.0) {
+        return Err(CalculateError::InvalidSettings(
+            "annealing cooling rate must be between 0 and 1".to_owned(),
+        ));
+    }
+    if !(params.initial_temperature > 0.0 && params.final_temperature > 0.0) {
+        return Err(CalculateError::InvalidSettings(
+            "annealing temperatures must be positive".to_owned(),
+        ));
+    }
+    if params.swaps_per_pixel == 0 || params.min_swaps_per_generation == 0 {
+        return Err(CalculateError::InvalidSettings(
+            "annealing swap counts must be at least 1".to_owned(),
+        ));
+    }
+
+    let grid = Grid::new(input);
+    let (mut pixels, mut generation, mut max_dist, mut temperature, scale, best) =
+        match &input.resume {
+            None => {
+                let pixels = grid.start(input);
+                let cost = pixels.iter().map(|p| p.h).sum::<i64>();
+                // temperatures are relative to the average pixel cost at the start
+                let scale = (cost as f64 / pixels.len() as f64).max(1.0);
+                let best = genetic::assignments(&pixels, settings.sidelen);
+                let max_dist = genetic::start_dist(input);
+                (pixels, 0, max_dist, params.initial_temperature, scale, best)
+            }
+            Some(SolverState::Annealing {
+                assignments,
+                generation,
+                max_dist,
+                temperature,
+                scale,
+                best,
+            }) if *max_dist > 0 && *temperature > 0.0 && *scale >= 1.0 => {
+                for assignments in [assignments, best] {
+                    validate_assignments(assignments, grid.source.len())
+                        .map_err(|_| SolverState::mismatch())?;
+                }
+                let pixels = grid.place(assignments);
+                (
+                    pixels,
+                    *generation,
+                    *max_dist,
+                    *temperature,
+                    *scale,
+                    best.clone(),
+                )
+            }
+            Some(_) => return Err(SolverState::mismatch()),
+        };
+    let mut cost = pixels.iter().map(|p| p.h).sum::<i64>();
+    let best_cost = grid.place(&best).iter().map(|p| p.h).sum::<i64>();
+    let mut best = (best_cost, best);
+
+    // generations until `final_temperature`, for the progress bar
+    let generations =
+        (params.final_temperature / params.initial_temperature).ln() / params.cooling_rate.ln();
+
+    let swaps_per_generation = params.swaps_per_pixel * pixels.len();
+    loop {
+        // every generation draws from its own seed, so a resumed run goes on exactly as it would
+        // have
+        let mut rng = frand::Rand::with_seed(SEED ^ generation);
+        let (swaps_made, cost_change) = grid.anneal_pass(
+            &mut pixels,
+            max_dist,
+            swaps_per_generation,
+            temperature * scale,
+            &mut rng,
+        );
+        cost += cost_change;
+        if cost < best.0 {
+            best = (cost, genetic::assignments(&pixels, settings.sidelen));
+        }
+
+        ctx.checkpoint().await?;
+
+        let cooled =
+            temperature <= params.final_temperature && swaps_made < params.min_swaps_per_generation;
+        if cooled || ctx.should_finish() {
+            break;
+        }
+        ctx.preview(input, &best.1);
+        ctx.progress((generation as f64 / generations.max(1.0)).min(1.0) as f32);
+
+        temperature *= params.cooling_rate;
+        max_dist = genetic::next_dist(max_dist);
+        generation += 1;
+        if ctx.should_pause() {
+            return Err(ctx.pause(SolverState::Annealing {
+                assignments: genetic::assignments(&pixels, settings.sidelen),
+                generation,
+                max_dist,
+                temperature,
+                scale,
+                best: best.1,
+            }));
+        }
+    }
+    Ok(best.1)
+}
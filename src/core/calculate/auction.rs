@@ -0,0 +1,162 @@
+use pathfinding::prelude::Weights;
+
+use crate::core::calculate::solver::{AssignmentSolver, SolveFuture, SolverContext, SolverInput};
+use crate::core::calculate::util::Algorithm;
+use crate::core::calculate::{CalculateError, ImgDiffWeights};
+
+/// Bertsekas' auction algorithm with epsilon scaling. Every target pixel bids for the source
+/// pixel that is the best deal at the current prices, raising its price by the margin over the
+/// second best plus ε. With a final ε of 1 the total cost is within `sidelen²` of the optimum.
+pub(crate) struct Auction;
+
+impl AssignmentSolver for Auction {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::new("Auction")
+    }
+
+    fn name(&self) -> &'static str {
+        "auction"
+    }
+
+    fn label(&self) -> &'static str {
+        "auction algorithm"
+    }
+
+    fn description(&self) -> &'static str {
+        "nearly as good as the optimal algorithm, in a fraction of the time"
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+/// ε is divided by this after every phase.
+const EPSILON_FACTOR: i64 = 8;
+/// Bids between progress reports and cancellation checks.
+const BIDS_PER_STEP: usize = 1024;
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    auction(
+        &ImgDiffWeights::new(input),
+        ctx,
+        |ctx, progress, assigned| {
+            ctx.progress(progress);
+            ctx.preview(
+                input,
+                &assigned.iter().map(|a| a.unwrap_or(0)).collect::<Vec<_>>(),
+            );
+        },
+    )
+    .await
+}
+
+/// Runs the auction over `weights`. `on_step` is called every [`BIDS_PER_STEP`] bids with the
+/// progress so far and the current, incomplete assignments.
+pub(crate) async fn auction(
+    weights: &ImgDiffWeights,
+    ctx: &mut SolverContext<'_>,
+    mut on_step: impl FnMut(&mut SolverContext<'_>, f32, &[Option<usize>]),
+) -> Result<Vec<usize>, CalculateError> {
+    // target pixels (rows) bid for source pixels (columns)
+    let n = weights.rows();
+
+    let mut epsilon = (weights.max_cost() / EPSILON_FACTOR).max(1);
+    let phases = {
+        let mut phases = 1;
+        let mut e = epsilon;
+        while e > 1 {
+            e = (e / EPSILON_FACTOR).max(1);
+            phases += 1;
+        }
+        phases
+    };
+
+    // prices carry over between phases, which is what makes the later ones cheap
+    let mut prices = vec![0i64; n];
+    let mut owner: Vec<Option<usize>> = vec![None; n];
+    let mut assigned: Vec<Option<usize>> = vec![None; n];
+    let mut unassigned = Vec::with_capacity(n);
+    for phase in 0..phases {
+        owner.fill(None);
+        assigned.fill(None);
+        unassigned.clear();
+        unassigned.extend((0..n).rev());
+
+        let mut bids = 0;
+        while let Some(bidder) = unassigned.pop() {
+            let (best, best_value, mut second_value) = best_two(weights, &prices, bidder);
+            // with a single pixel there is no competition
+            if second_value == i64::MIN {
+                second_value = best_value;
+            }
+
+            prices[best] += best_value - second_value + epsilon;
+            assigned[bidder] = Some(best);
+            if let Some(outbid) = owner[best].replace(bidder) {
+                assigned[outbid] = None;
+                unassigned.push(outbid);
+            }
+
+            bids += 1;
+            if bids % BIDS_PER_STEP == 0 {
+                ctx.checkpoint().await?;
+                if ctx.should_finish() {
+                    return Ok(weights.complete_greedily(&assigned));
+                }
+                let done = (n - unassigned.len()) as f32 / n as f32;
+                on_step(ctx, (phase as f32 + done) / phases as f32, &assigned);
+            }
+        }
+
+        epsilon = (epsilon / EPSILON_FACTOR).max(1);
+    }
+
+    Ok(assigned.into_iter().map(Option::unwrap).collect())
+}
+
+/// The most valuable source pixel for `bidder` at the current prices, its value and the value of
+/// the runner-up. Same as taking `weights.at(bidder, col) - prices[col]` for every column, but
+/// walks the source image row by row instead of dividing every index.
+#[inline(always)]
+fn best_two(weights: &ImgDiffWeights, prices: &[i64], bidder: usize) -> (usize, i64, i64) {
+    let sidelen = weights.sidelen;
+    let target_pos = ((bidder % sidelen) as u32, (bidder / sidelen) as u32);
+    let target_color = weights.target[bidder];
+    let color_weight = weights.weights[bidder];
+
+    let mut best = 0;
+    let mut best_value = i64::MIN;
+    let mut second_value = i64::MIN;
+    let rows = weights
+        .source
+        .chunks_exact(sidelen)
+        .zip(prices.chunks_exact(sidelen));
+    for (y, (source_row, price_row)) in rows.enumerate() {
+        for (x, (&color, &price)) in source_row.iter().zip(price_row).enumerate() {
+            let value = -weights.model.cost(
+                target_pos,
+                (x as u32, y as u32),
+                target_color,
+                color,
+                color_weight,
+                weights.proximity_importance,
+            ) - price;
+            if value > best_value {
+                second_value = best_value;
+                best_value = value;
+                best = y * sidelen + x;
+            } else if value > second_value {
+                second_value = value;
+            }
+        }
+    }
+    (best, best_value, second_value)
+}
@@ -0,0 +1,187 @@
+//! How much it costs to put a source pixel on a target pixel: a color term, weighted by the
+//! target pixel's importance, plus a spatial term for how far the pixel moves.
+//!
+//! Colors are converted into the model's [`ColorSpace`] once, before a solver starts, so the
+//! solvers only ever compare integer coordinates. Every color space is scaled so black and white
+//! are as far apart as in RGB, which keeps proximity importance meaning about the same whichever
+//! one is picked.
+
+use palette::{FromColor, Lab, Oklab, Srgb};
+use serde::{Deserialize, Serialize};
+
+use crate::core::calculate::CalculateError;
+
+/// A color in a [`CostModel`]'s color space.
+pub type CostColor = [i32; 3];
+
+/// The widest image the solvers accept.
+pub const MAX_SIDELEN: u32 = 1024;
+
+/// The most a single pair can cost; higher costs are clamped to this. The costs of all pixels of
+/// a [`MAX_SIDELEN`]-wide image add up to less than `i64::MAX / 4`, which leaves the solvers room
+/// for prices and labels on top.
+pub const MAX_COST: i64 = i64::MAX >> 22;
+
+const _: () = assert!((MAX_SIDELEN as i64).pow(2) * MAX_COST <= i64::MAX / 4);
+
+/// How far apart black and white are in RGB.
+const RGB_BLACK_TO_WHITE: f32 = 441.67295; // 255 * sqrt(3)
+
+/// The color space colors are compared in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// Squared distance between the raw RGB values.
+    #[default]
+    Rgb,
+    /// Squared ΔE in OKLab, which follows perceived differences closely.
+    Oklab,
+    /// Squared ΔE (CIE76) in CIELAB.
+    Cielab,
+    /// Only the perceived lightness, from OKLab; hue and saturation don't count.
+    Luminance,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 4] = [
+        ColorSpace::Rgb,
+        ColorSpace::Oklab,
+        ColorSpace::Cielab,
+        ColorSpace::Luminance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorSpace::Rgb => "rgb",
+            ColorSpace::Oklab => "oklab",
+            ColorSpace::Cielab => "cielab",
+            ColorSpace::Luminance => "luminance",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorSpace::Rgb => "RGB",
+            ColorSpace::Oklab => "OKLab ΔE",
+            ColorSpace::Cielab => "CIELAB ΔE",
+            ColorSpace::Luminance => "luminance only",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|space| space.name() == name)
+    }
+}
+
+/// The cost function the solvers minimize, picked in [`super::util::GenerationSettings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostModel {
+    pub color_space: ColorSpace,
+    /// The spatial term is `(d² · proximity importance)` to this power, `d` being how far the
+    /// pixel moves. Higher values punish long moves harder compared to short ones.
+    pub spatial_exponent: u32,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            color_space: ColorSpace::Rgb,
+            spatial_exponent: 2,
+        }
+    }
+}
+
+impl CostModel {
+    pub const SPATIAL_EXPONENTS: std::ops::RangeInclusive<u32> = 1..=3;
+
+    pub(crate) fn check(&self) -> Result<(), CalculateError> {
+        if !Self::SPATIAL_EXPONENTS.contains(&self.spatial_exponent) {
+            return Err(CalculateError::InvalidSettings(format!(
+                "the spatial exponent must be between {} and {}",
+                Self::SPATIAL_EXPONENTS.start(),
+                Self::SPATIAL_EXPONENTS.end()
+            )));
+        }
+        Ok(())
+    }
+
+    /// `rgb` in the model's color space.
+    pub fn color(&self, (r, g, b): (u8, u8, u8)) -> CostColor {
+        let scaled =
+            |c: f32, black_to_white: f32| (c * RGB_BLACK_TO_WHITE / black_to_white).round();
+        let srgb = Srgb::new(r, g, b).into_format::<f32>();
+        match self.color_space {
+            ColorSpace::Rgb => [r as i32, g as i32, b as i32],
+            ColorSpace::Oklab => {
+                let lab = Oklab::from_color(srgb);
+                [lab.l, lab.a, lab.b].map(|c| scaled(c, 1.0) as i32)
+            }
+            ColorSpace::Cielab => {
+                let lab: Lab = Lab::from_color(srgb);
+                [lab.l, lab.a, lab.b].map(|c| scaled(c, 100.0) as i32)
+            }
+            ColorSpace::Luminance => [scaled(Oklab::from_color(srgb).l, 1.0) as i32, 0, 0],
+        }
+    }
+
+    pub fn colors(&self, pixels: &[(u8, u8, u8)]) -> Vec<CostColor> {
+        pixels.iter().map(|&rgb| self.color(rgb)).collect()
+    }
+
+    /// The cost of moving a pixel of color `a` from `apos` to `bpos`, where the target color is
+    /// `b`. Never more than [`MAX_COST`].
+    #[inline(always)]
+    pub fn cost(
+        &self,
+        apos: (u32, u32),
+        bpos: (u32, u32),
+        a: CostColor,
+        b: CostColor,
+        color_weight: i64,
+        spatial_weight: i64,
+    ) -> i64 {
+        let spatial =
+            (apos.0 as i64 - bpos.0 as i64).pow(2) + (apos.1 as i64 - bpos.1 as i64).pow(2);
+        color_distance(a, b)
+            .saturating_mul(color_weight)
+            .saturating_add(self.spatial_cost(spatial, spatial_weight))
+            .min(MAX_COST)
+    }
+
+    /// The spatial term for a move `dist2` squared pixels long, which no pair that far apart
+    /// costs less than.
+    #[inline(always)]
+    pub fn spatial_cost(&self, dist2: i64, spatial_weight: i64) -> i64 {
+        let spatial = dist2.saturating_mul(spatial_weight);
+        match self.spatial_exponent {
+            1 => spatial,
+            2 => spatial.saturating_mul(spatial),
+            exponent => spatial.saturating_pow(exponent),
+        }
+        .min(MAX_COST)
+    }
+}
+
+/// Squared distance between two colors.
+#[inline(always)]
+pub(crate) fn color_distance(a: CostColor, b: CostColor) -> i64 {
+    (a[0] as i64 - b[0] as i64).pow(2)
+        + (a[1] as i64 - b[1] as i64).pow(2)
+        + (a[2] as i64 - b[2] as i64).pow(2)
+}
+
+/// Upper bound for the distance between any two of `colors`: the diagonal of their bounding
+/// box.
+pub(crate) fn max_color_distance<'a>(colors: impl IntoIterator<Item = &'a CostColor>) -> i64 {
+    let mut min = [i32::MAX; 3];
+    let mut max = [i32::MIN; 3];
+    for color in colors {
+        for ((lo, hi), &c) in min.iter_mut().zip(&mut max).zip(color) {
+            *lo = (*lo).min(c);
+            *hi = (*hi).max(c);
+        }
+    }
+    if min[0] > max[0] {
+        return 0;
+    }
+    color_distance(min, max)
+}
@@ -1,14 +1,14 @@
-use crate::app::SeedColor;
-use crate::app::calculate;
-use crate::app::calculate::SWAPS_PER_GENERATION_PER_PIXEL;
-use crate::app::preset::UnprocessedPreset;
-
-use std::error::Error;
+use crate::core::calculate;
+use crate::core::calculate::SWAPS_PER_GENERATION_PER_PIXEL;
+use crate::core::morph_sim::SeedColor;
+use crate::core::preset::UnprocessedPreset;
 
 use std::sync::Arc;
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicBool;
 use std::sync::mpsc;
 
+use super::CalculateError;
+use super::JobMsg;
 use super::ProgressMsg;
 
 use super::GenerationSettings;
@@ -19,7 +19,7 @@ pub struct PixelData {
     pub last_edited: u32,
 }
 impl PixelData {
-    pub(crate) fn init_canvas(frame_count: u32) -> Vec<PixelData> {
+    pub fn init_canvas(frame_count: u32) -> Vec<PixelData> {
         vec![
             PixelData {
                 stroke_id: 0,
@@ -32,17 +32,17 @@ impl PixelData {
 
 pub const DRAWING_CANVAS_SIZE: usize = 128;
 
-use super::heuristic;
+use super::cost::{CostColor, CostModel};
 
 #[derive(Clone, Copy)]
 pub(crate) struct DrawingPixel {
-    pub(crate) src_x: u16,
-    pub(crate) src_y: u16,
+    pub(crate) src_x: u32,
+    pub(crate) src_y: u32,
     pub(crate) h: i64, // current heuristic value
 }
 
 impl DrawingPixel {
-    pub(crate) fn new(src_x: u16, src_y: u16, h: i64) -> Self {
+    pub(crate) fn new(src_x: u32, src_y: u32, h: i64) -> Self {
         Self { src_x, src_y, h }
     }
 
@@ -53,24 +53,17 @@ impl DrawingPixel {
     #[inline(always)]
     pub(crate) fn calc_drawing_heuristic(
         &self,
-        target_pos: (u16, u16),
-        target_col: (u8, u8, u8),
+        target_pos: (u32, u32),
+        target_col: CostColor,
         weight: i64,
-        colors: &[SeedColor],
+        colors: &[CostColor],
+        model: &CostModel,
         proximity_importance: i64,
     ) -> i64 {
-        heuristic(
+        model.cost(
             (self.src_x, self.src_y),
             target_pos,
-            {
-                let rgba =
-                    colors[self.src_y as usize * DRAWING_CANVAS_SIZE + self.src_x as usize].rgba;
-                (
-                    (rgba[0] * 256.0) as u8,
-                    (rgba[1] * 256.0) as u8,
-                    (rgba[2] * 256.0) as u8,
-                )
-            },
+            colors[self.src_y as usize * DRAWING_CANVAS_SIZE + self.src_x as usize],
             target_col,
             weight,
             proximity_importance,
@@ -78,6 +71,20 @@ impl DrawingPixel {
     }
 }
 
+/// The canvas colors in the cost model's color space.
+fn canvas_colors(colors: &[SeedColor], model: &CostModel) -> Vec<CostColor> {
+    colors
+        .iter()
+        .map(|c| {
+            model.color((
+                (c.rgba[0] * 256.0) as u8,
+                (c.rgba[1] * 256.0) as u8,
+                (c.rgba[2] * 256.0) as u8,
+            ))
+        })
+        .collect()
+}
+
 pub(crate) const STROKE_REWARD: i64 = -10000000000;
 
 pub(crate) fn stroke_reward(
@@ -128,36 +135,39 @@ pub(crate) fn stroke_reward(
 pub fn drawing_process_genetic(
     source: UnprocessedPreset,
     settings: GenerationSettings,
-    tx: mpsc::SyncSender<ProgressMsg>,
+    tx: mpsc::SyncSender<JobMsg>,
     colors: Arc<std::sync::RwLock<Vec<SeedColor>>>,
     pixel_data: Arc<std::sync::RwLock<Vec<PixelData>>>,
     frame_count: u32,
-    my_id: u32,
-    current_id: Arc<AtomicU32>,
-) -> Result<(), Box<dyn Error>> {
-    let source_img =
-        image::ImageBuffer::from_raw(source.width, source.height, source.source_img.clone())
-            .unwrap();
+    cancel: Arc<AtomicBool>,
+) -> Result<(), CalculateError> {
+    let job_id = settings.id;
+    let source_img = calculate::util::source_image(&source)?;
     let (source_pixels, target_pixels, weights) =
         calculate::util::get_images(source_img, &settings)?;
+    let proximity_importance = settings.scaled_proximity_importance();
+    let model = settings.cost_model;
+    model.check()?;
+    let target_pixels = model.colors(&target_pixels);
 
     let mut pixels = {
-        let read_colors: Vec<SeedColor> = colors.read().unwrap().clone();
+        let read_colors = canvas_colors(&colors.read().unwrap(), &model);
         //let read_pixel_data: Vec<PixelData> = pixel_data.read().unwrap().clone();
 
         source_pixels
             .iter()
             .enumerate()
             .map(|(i, _)| {
-                let x = (i as u32 % settings.sidelen) as u16;
-                let y = (i as u32 / settings.sidelen) as u16;
+                let x = i as u32 % settings.sidelen;
+                let y = i as u32 / settings.sidelen;
                 let mut p = DrawingPixel::new(x, y, 0);
                 let h = p.calc_drawing_heuristic(
                     (x, y),
                     target_pixels[i],
                     weights[i],
                     &read_colors,
-                    settings.proximity_importance,
+                    &model,
+                    proximity_importance,
                     // &read_pixel_data,
                 ) + STROKE_REWARD;
                 p.update_heuristic(h);
@@ -174,10 +184,7 @@ pub fn drawing_process_genetic(
     let swaps_per_generation = SWAPS_PER_GENERATION_PER_PIXEL * pixels.len();
 
     loop {
-        let colors: Vec<SeedColor> = {
-            let r = colors.read().unwrap();
-            r.clone()
-        };
+        let canvas = canvas_colors(&colors.read().unwrap(), &model);
         let pixel_data = {
             let r = pixel_data.read().unwrap();
             r.clone()
@@ -186,16 +193,16 @@ pub fn drawing_process_genetic(
 
         for _ in 0..swaps_per_generation {
             let apos = rng.gen_range(0..pixels.len() as u64) as usize;
-            let ax = apos as u16 % settings.sidelen as u16;
-            let ay = apos as u16 / settings.sidelen as u16;
+            let ax = apos as u32 % settings.sidelen;
+            let ay = apos as u32 / settings.sidelen;
 
             //let stroke_id = pixel_data[apos].stroke_id as usize;
             let max_dist_a = max_dist(frame_count.saturating_sub(pixel_data[apos].last_edited));
 
-            let bx = (ax as i16 + rng.gen_range(-(max_dist_a as i16)..(max_dist_a as i16 + 1)))
-                .clamp(0, settings.sidelen as i16 - 1) as u16;
-            let by = (ay as i16 + rng.gen_range(-(max_dist_a as i16)..(max_dist_a as i16 + 1)))
-                .clamp(0, settings.sidelen as i16 - 1) as u16;
+            let bx = (ax as i32 + rng.gen_range(-(max_dist_a as i32)..(max_dist_a as i32 + 1)))
+                .clamp(0, settings.sidelen as i32 - 1) as u32;
+            let by = (ay as i32 + rng.gen_range(-(max_dist_a as i32)..(max_dist_a as i32 + 1)))
+                .clamp(0, settings.sidelen as i32 - 1) as u32;
             let bpos = by as usize * settings.sidelen as usize + bx as usize;
 
             let max_dist_b = max_dist(frame_count.saturating_sub(pixel_data[bpos].last_edited));
@@ -212,16 +219,18 @@ pub fn drawing_process_genetic(
                 (bx, by),
                 t_b,
                 weights[bpos],
-                &colors,
-                settings.proximity_importance,
+                &canvas,
+                &model,
+                proximity_importance,
             ) + stroke_reward(bpos, apos, &pixel_data, &pixels, frame_count);
 
             let b_on_a_h = pixels[bpos].calc_drawing_heuristic(
                 (ax, ay),
                 t_a,
                 weights[apos],
-                &colors,
-                settings.proximity_importance,
+                &canvas,
+                &model,
+                proximity_importance,
             ) + stroke_reward(apos, bpos, &pixel_data, &pixels, frame_count);
 
             let improvement_a = pixels[apos].h - b_on_a_h;
@@ -252,11 +261,16 @@ pub fn drawing_process_genetic(
                 .iter()
                 .map(|p| p.src_y as usize * settings.sidelen as usize + p.src_x as usize)
                 .collect::<Vec<_>>();
-            tx.send(ProgressMsg::UpdateAssignments(assignments))?;
+            // the app is gone if nobody is listening anymore
+            let msg = ProgressMsg::UpdateAssignments(assignments);
+            if tx.send(JobMsg { job_id, msg }).is_err() {
+                return Ok(());
+            }
         }
-        if my_id != current_id.load(std::sync::atomic::Ordering::Relaxed) {
-            tx.send(ProgressMsg::Cancelled).unwrap();
-            return Ok(());
+        if cancel.load(std::sync::atomic::Ordering::Relaxed) {
+            let msg = ProgressMsg::Cancelled;
+            tx.send(JobMsg { job_id, msg }).ok();
+            return Err(CalculateError::Cancelled);
         }
 
         //max_dist = (max_dist as f32 * 0.99).max(4.0) as u32;
@@ -0,0 +1,53 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::validate::ValidationError;
+
+/// Why a generation job stopped without a result. Serializable so it keeps its structure when
+/// sent from the web worker in [`super::ProgressMsg::Error`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CalculateError {
+    /// The source image can't be used.
+    InvalidSource(ValidationError),
+    /// The custom target's pixel data doesn't match its dimensions.
+    InvalidTarget { width: u32, height: u32, len: usize },
+    /// The settings can't produce a result, e.g. a zero resolution.
+    InvalidSettings(String),
+    /// The job was cancelled before it finished.
+    Cancelled,
+    /// The job was paused; its state was sent in [`super::ProgressMsg::Paused`].
+    Paused,
+    /// Something that should not happen, like an embedded asset failing to decode.
+    Internal(String),
+}
+
+impl fmt::Display for CalculateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalculateError::InvalidSource(e) => write!(f, "invalid source image: {e}"),
+            CalculateError::InvalidTarget { width, height, len } => write!(
+                f,
+                "invalid target image: {width}×{height} with {len} bytes of pixel data"
+            ),
+            CalculateError::InvalidSettings(msg) => write!(f, "invalid settings: {msg}"),
+            CalculateError::Cancelled => write!(f, "cancelled"),
+            CalculateError::Paused => write!(f, "paused"),
+            CalculateError::Internal(msg) => write!(f, "internal error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for CalculateError {}
+
+impl From<ValidationError> for CalculateError {
+    fn from(e: ValidationError) -> Self {
+        CalculateError::InvalidSource(e)
+    }
+}
+
+impl From<image::ImageError> for CalculateError {
+    fn from(e: image::ImageError) -> Self {
+        CalculateError::Internal(e.to_string())
+    }
+}
@@ -0,0 +1,332 @@
+use crate::core::calculate::cost::{CostColor, CostModel};
+use crate::core::calculate::solver::{
+    AssignmentSolver, SolveFuture, SolverContext, SolverInput, SolverState,
+};
+use crate::core::calculate::util::Algorithm;
+use crate::core::calculate::{CalculateError, Pixel, SWAPS_PER_GENERATION_PER_PIXEL};
+use crate::core::validate::validate_assignments;
+
+/// Repeatedly swaps random pairs of pixels when that lowers their combined cost, narrowing the
+/// swap distance each generation.
+pub(crate) struct Genetic;
+
+impl AssignmentSolver for Genetic {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::GENETIC
+    }
+
+    fn name(&self) -> &'static str {
+        "genetic"
+    }
+
+    fn label(&self) -> &'static str {
+        "fast algorithm"
+    }
+
+    fn description(&self) -> &'static str {
+        "gets close to the optimal result in a fraction of the time"
+    }
+
+    fn warm_starts(&self) -> bool {
+        true
+    }
+
+    fn pauses(&self) -> bool {
+        true
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+/// A result being refined only needs fine adjustments, so swaps start at this fraction of the
+/// image apart instead of across all of it.
+const REFINE_DIST_FRACTION: u32 = 8;
+
+/// How far apart the first generation's swaps may be.
+pub(crate) fn start_dist(input: &SolverInput) -> u32 {
+    let sidelen = input.settings.sidelen;
+    if input.initial.is_some() {
+        (sidelen / REFINE_DIST_FRACTION).max(2)
+    } else {
+        sidelen
+    }
+}
+
+/// Shrinks the swap distance for the next generation.
+pub(crate) fn next_dist(max_dist: u32) -> u32 {
+    (max_dist as f32 * 0.99).max(2.0) as u32
+}
+
+/// Where a run of the genetic solvers is: the pixels, the generation and the swap distance.
+pub(crate) struct Run {
+    pub pixels: Vec<Pixel>,
+    pub generation: u64,
+    pub max_dist: u32,
+    pub first_dist: u32,
+}
+
+impl Run {
+    /// The first generation of a new run, or where a paused one left off.
+    pub fn start(grid: &Grid<'_>, input: &SolverInput) -> Result<Self, CalculateError> {
+        match &input.resume {
+            None => {
+                let first_dist = start_dist(input);
+                Ok(Self {
+                    pixels: grid.start(input),
+                    generation: 0,
+                    max_dist: first_dist,
+                    first_dist,
+                })
+            }
+            Some(SolverState::Genetic {
+                assignments,
+                generation,
+                max_dist,
+                first_dist,
+            }) if *max_dist > 0 && *first_dist > 0 => {
+                validate_assignments(assignments, grid.source.len())
+                    .map_err(|_| SolverState::mismatch())?;
+                Ok(Self {
+                    pixels: grid.place(assignments),
+                    generation: *generation,
+                    max_dist: *max_dist,
+                    first_dist: *first_dist,
+                })
+            }
+            Some(_) => Err(SolverState::mismatch()),
+        }
+    }
+
+    /// The state to save when pausing before the next generation.
+    pub fn paused(&self, sidelen: u32) -> SolverState {
+        SolverState::Genetic {
+            assignments: assignments(&self.pixels, sidelen),
+            generation: self.generation,
+            max_dist: self.max_dist,
+            first_dist: self.first_dist,
+        }
+    }
+}
+
+/// The images being matched at one resolution, borrowed from a [`SolverInput`] or from a
+/// scaled-down copy of it.
+pub(crate) struct Grid<'a> {
+    pub source: &'a [CostColor],
+    pub target: &'a [CostColor],
+    pub weights: &'a [i64],
+    pub sidelen: u32,
+    pub proximity_importance: i64,
+    pub model: CostModel,
+}
+
+impl<'a> Grid<'a> {
+    pub fn new(input: &'a SolverInput) -> Self {
+        Self {
+            source: &input.source_colors,
+            target: &input.target_colors,
+            weights: &input.weights,
+            sidelen: input.settings.sidelen,
+            proximity_importance: input.settings.scaled_proximity_importance(),
+            model: input.settings.cost_model,
+        }
+    }
+
+    /// The pixels to start from: [`SolverInput::initial`] if given, or else every pixel where
+    /// it is in the source image.
+    pub fn start(&self, input: &SolverInput) -> Vec<Pixel> {
+        match &input.initial {
+            Some(initial) => self.place(initial),
+            None => self.place(&(0..self.source.len()).collect::<Vec<_>>()),
+        }
+    }
+
+    /// One pixel per target position, holding the source pixel `assignments` puts there.
+    pub fn place(&self, assignments: &[usize]) -> Vec<Pixel> {
+        let sidelen = self.sidelen as usize;
+        assignments
+            .iter()
+            .enumerate()
+            .map(|(i, &src)| {
+                let mut p = Pixel::new(
+                    (src % sidelen) as u32,
+                    (src / sidelen) as u32,
+                    self.source[src],
+                    0,
+                );
+                let h = p.calc_heuristic(
+                    &self.model,
+                    ((i % sidelen) as u32, (i / sidelen) as u32),
+                    self.target[i],
+                    self.weights[i],
+                    self.proximity_importance,
+                );
+                p.update_heuristic(h);
+                p
+            })
+            .collect()
+    }
+
+    /// Tries `swaps` random swaps between pixels at most `max_dist` apart on each axis, keeping
+    /// those that lower the combined cost. Returns how many were kept.
+    pub fn swap_pass(
+        &self,
+        pixels: &mut [Pixel],
+        max_dist: u32,
+        swaps: usize,
+        rng: &mut frand::Rand,
+    ) -> usize {
+        self.anneal_pass(pixels, max_dist, swaps, 0.0, rng).0
+    }
+
+    /// Like [`Self::swap_pass`], but also keeps a swap that raises the cost by `d` with
+    /// probability `exp(-d / temperature)`. Returns how many swaps were kept and how much they
+    /// changed the total cost.
+    pub fn anneal_pass(
+        &self,
+        pixels: &mut [Pixel],
+        max_dist: u32,
+        swaps: usize,
+        temperature: f64,
+        rng: &mut frand::Rand,
+    ) -> (usize, i64) {
+        let whole = Tile {
+            x: 0,
+            y: 0,
+            width: self.sidelen as usize,
+            height: self.sidelen as usize,
+        };
+        self.anneal_tile(pixels, whole, max_dist, swaps, temperature, rng)
+    }
+
+    /// [`Self::anneal_pass`] on just the pixels of `tile`, given in reading order. Both pixels
+    /// of every swap are inside the tile, so disjoint tiles can be worked on at the same time.
+    pub fn anneal_tile(
+        &self,
+        pixels: &mut [Pixel],
+        tile: Tile,
+        max_dist: u32,
+        swaps: usize,
+        temperature: f64,
+        rng: &mut frand::Rand,
+    ) -> (usize, i64) {
+        let sidelen = self.sidelen as usize;
+        let (min_x, max_x) = (tile.x as i32, (tile.x + tile.width) as i32 - 1);
+        let (min_y, max_y) = (tile.y as i32, (tile.y + tile.height) as i32 - 1);
+        // distances past the edge of the grid are clamped anyway
+        let max_dist = max_dist.min(self.sidelen) as i32;
+        let mut swaps_made = 0;
+        let mut cost_change = 0;
+        for _ in 0..swaps {
+            let apos = rng.gen_range(0..pixels.len() as u32) as usize;
+            let ax = (tile.x + apos % tile.width) as u32;
+            let ay = (tile.y + apos / tile.width) as u32;
+            let bx =
+                (ax as i32 + rng.gen_range(-max_dist..(max_dist + 1))).clamp(min_x, max_x) as u32;
+            let by =
+                (ay as i32 + rng.gen_range(-max_dist..(max_dist + 1))).clamp(min_y, max_y) as u32;
+            let bpos = (by as usize - tile.y) * tile.width + (bx as usize - tile.x);
+
+            // the target image is indexed by position in the whole grid
+            let ta = ay as usize * sidelen + ax as usize;
+            let tb = by as usize * sidelen + bx as usize;
+            let t_a = self.target[ta];
+            let t_b = self.target[tb];
+
+            let a_on_b_h = pixels[apos].calc_heuristic(
+                &self.model,
+                (bx, by),
+                t_b,
+                self.weights[tb],
+                self.proximity_importance,
+            );
+
+            let b_on_a_h = pixels[bpos].calc_heuristic(
+                &self.model,
+                (ax, ay),
+                t_a,
+                self.weights[ta],
+                self.proximity_importance,
+            );
+
+            let improvement_a = pixels[apos].h - b_on_a_h;
+            let improvement_b = pixels[bpos].h - a_on_b_h;
+            let improvement = improvement_a + improvement_b;
+            // only annealing passes draw here, so greedy ones don't depend on the temperature
+            let accept = improvement > 0
+                || (temperature > 0.0
+                    && improvement < 0
+                    && rng.gen_range(0.0..1.0f64) < (improvement as f64 / temperature).exp());
+            if accept {
+                // swap
+                pixels.swap(apos, bpos);
+                pixels[apos].update_heuristic(b_on_a_h);
+                pixels[bpos].update_heuristic(a_on_b_h);
+                swaps_made += 1;
+                cost_change -= improvement;
+            }
+        }
+        (swaps_made, cost_change)
+    }
+}
+
+/// A rectangle of the grid.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The source index of every pixel, in target order.
+pub(crate) fn assignments(pixels: &[Pixel], sidelen: u32) -> Vec<usize> {
+    pixels
+        .iter()
+        .map(|p| p.src_y as usize * sidelen as usize + p.src_x as usize)
+        .collect()
+}
+
+/// Every generation draws from its own seed, so a resumed run goes on exactly as it would have.
+const SEED: u64 = 12345;
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    let settings = &input.settings;
+    let grid = Grid::new(input);
+    let mut run = Run::start(&grid, input)?;
+    let swaps_per_generation = SWAPS_PER_GENERATION_PER_PIXEL * run.pixels.len();
+
+    loop {
+        let mut rng = frand::Rand::with_seed(SEED ^ run.generation);
+        let swaps_made = grid.swap_pass(
+            &mut run.pixels,
+            run.max_dist,
+            swaps_per_generation,
+            &mut rng,
+        );
+
+        ctx.checkpoint().await?;
+
+        let assignments = assignments(&run.pixels, settings.sidelen);
+        //debug_print(format!("max_dist = {max_dist}, swaps made = {swaps_made}"));
+        if ctx.should_finish() || (run.max_dist < 4 && swaps_made < 10) {
+            return Ok(assignments);
+        }
+        ctx.preview(input, &assignments);
+        ctx.progress(1.0 - run.max_dist as f32 / run.first_dist as f32);
+
+        run.max_dist = next_dist(run.max_dist);
+        run.generation += 1;
+        if ctx.should_pause() {
+            return Err(ctx.pause(run.paused(settings.sidelen)));
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Importance weights for custom targets. The built-in target comes with a hand-painted weight
+//! map, `weights256.png`; for any other image the weights are made from the image itself.
+//!
+//! Each cue gives a map from 0 to 1: edge magnitude, local contrast and a bias towards the
+//! center. The weights are their blend, from [`MIN_WEIGHT`] where no cue picks a pixel out up to
+//! 255.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::calculate::util::SourceImg;
+
+/// The weight of pixels no cue picks out, so their color still counts a little.
+pub const MIN_WEIGHT: i64 = 32;
+
+/// Cue maps are worked out over neighborhoods this fraction of the image wide, so they look the
+/// same at every resolution.
+const NEIGHBORHOOD_FRACTION: usize = 64;
+
+/// How much each cue counts towards a custom target's weights. All zero gives every pixel the
+/// same weight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AutoWeights {
+    /// Gradient magnitude, which picks out outlines and features like eyes and mouths.
+    pub edges: f32,
+    /// How much the lightness varies around each pixel, which picks out detailed regions.
+    pub contrast: f32,
+    /// Closeness to the center of the image, where the subject usually is.
+    pub center: f32,
+}
+
+impl AutoWeights {
+    /// Every pixel weighs the same, as custom targets did before automatic weights.
+    pub fn uniform() -> Self {
+        Self::default()
+    }
+
+    /// The blend new jobs start with.
+    pub fn automatic() -> Self {
+        Self {
+            edges: 0.4,
+            contrast: 0.35,
+            center: 0.25,
+        }
+    }
+
+    pub fn is_uniform(&self) -> bool {
+        self.total() <= 0.0
+    }
+
+    fn total(&self) -> f32 {
+        self.edges.max(0.0) + self.contrast.max(0.0) + self.center.max(0.0)
+    }
+
+    /// The weight of every pixel of `target`, in reading order.
+    pub fn weights(&self, target: &SourceImg) -> Vec<i64> {
+        let (width, height) = (target.width() as usize, target.height() as usize);
+        let total = self.total();
+        if total <= 0.0 {
+            return vec![255; width * height];
+        }
+        let lightness = target
+            .pixels()
+            .map(|p| (0.2126 * p[0] as f32 + 0.7152 * p[1] as f32 + 0.0722 * p[2] as f32) / 255.0)
+            .collect::<Vec<_>>();
+        let image = Lightness {
+            values: &lightness,
+            width,
+            height,
+            radius: (width.max(height) / NEIGHBORHOOD_FRACTION).max(1),
+        };
+
+        let cues: [(f32, Cue); 3] = [
+            (self.edges, |image| image.edges()),
+            (self.contrast, |image| image.contrast()),
+            (self.center, |image| image.center()),
+        ];
+        let mut blend = vec![0.0; lightness.len()];
+        for (amount, cue) in cues {
+            if amount <= 0.0 {
+                continue;
+            }
+            for (b, m) in blend.iter_mut().zip(normalized(cue(&image))) {
+                *b += amount / total * m;
+            }
+        }
+        blend
+            .into_iter()
+            .map(|b| MIN_WEIGHT + ((255 - MIN_WEIGHT) as f32 * b.clamp(0.0, 1.0)).round() as i64)
+            .collect()
+    }
+}
+
+/// Makes one cue's map.
+type Cue = fn(&Lightness<'_>) -> Vec<f32>;
+
+/// The lightness of every pixel of an image, from 0 to 1.
+struct Lightness<'a> {
+    values: &'a [f32],
+    width: usize,
+    height: usize,
+    /// How far around a pixel its neighborhood reaches.
+    radius: usize,
+}
+
+impl Lightness<'_> {
+    fn at(&self, x: isize, y: isize) -> f32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.values[y * self.width + x]
+    }
+
+    /// Sobel gradient magnitude, spread over the neighborhood so thin outlines cover some area.
+    fn edges(&self) -> Vec<f32> {
+        let mut magnitude = Vec::with_capacity(self.values.len());
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let p = |dx, dy| self.at(x + dx, y + dy);
+                let gx = p(1, -1) + 2.0 * p(1, 0) + p(1, 1) - p(-1, -1) - 2.0 * p(-1, 0) - p(-1, 1);
+                let gy = p(-1, 1) + 2.0 * p(0, 1) + p(1, 1) - p(-1, -1) - 2.0 * p(0, -1) - p(1, -1);
+                magnitude.push((gx * gx + gy * gy).sqrt());
+            }
+        }
+        box_mean(&magnitude, self.width, self.height, self.radius)
+    }
+
+    /// Standard deviation of the lightness over a neighborhood twice as wide as the edges'.
+    fn contrast(&self) -> Vec<f32> {
+        let radius = 2 * self.radius;
+        let squares = self.values.iter().map(|l| l * l).collect::<Vec<_>>();
+        let mean = box_mean(self.values, self.width, self.height, radius);
+        let mean_square = box_mean(&squares, self.width, self.height, radius);
+        mean.iter()
+            .zip(mean_square)
+            .map(|(m, sq)| (sq - m * m).max(0.0).sqrt())
+            .collect()
+    }
+
+    /// A gaussian around the center, as wide as a third of the shorter side.
+    fn center(&self) -> Vec<f32> {
+        let sigma = self.width.min(self.height) as f32 / 3.0;
+        let (cx, cy) = (
+            (self.width as f32 - 1.0) / 2.0,
+            (self.height as f32 - 1.0) / 2.0,
+        );
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let d2 = (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2);
+                (-d2 / (2.0 * sigma * sigma)).exp()
+            })
+            .collect()
+    }
+}
+
+/// The mean of `values` over the square `radius` around every pixel, cut off at the edges of the
+/// image, from a summed-area table.
+fn box_mean(values: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let stride = width + 1;
+    let mut sums = vec![0.0f64; stride * (height + 1)];
+    for y in 0..height {
+        let mut row = 0.0;
+        for x in 0..width {
+            row += values[y * width + x] as f64;
+            sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row;
+        }
+    }
+    let mut means = Vec::with_capacity(values.len());
+    for y in 0..height {
+        let (y0, y1) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+        for x in 0..width {
+            let (x0, x1) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+            let sum = sums[y1 * stride + x1] - sums[y0 * stride + x1] - sums[y1 * stride + x0]
+                + sums[y0 * stride + x0];
+            means.push((sum / ((x1 - x0) * (y1 - y0)) as f64) as f32);
+        }
+    }
+    means
+}
+
+/// `map` scaled so its largest value is 1. A map that is zero everywhere stays that way.
+fn normalized(mut map: Vec<f32>) -> Vec<f32> {
+    let max = map.iter().copied().fold(0.0, f32::max);
+    if max > 0.0 {
+        map.iter_mut().for_each(|m| *m /= max);
+    }
+    map
+}
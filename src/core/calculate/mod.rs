@@ -0,0 +1,443 @@
+use std::sync::Arc;
+mod annealing;
+mod auction;
+pub mod cost;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod drawing_process;
+mod error;
+mod genetic;
+pub mod importance;
+mod multires;
+mod optimal;
+mod parallel;
+mod sliced;
+pub mod solver;
+mod sparse;
+pub mod util;
+
+pub use error::CalculateError;
+
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub mod worker;
+
+fn _debug_print(s: String) {
+    #[cfg(all(target_arch = "wasm32", feature = "web"))]
+    web_sys::console::log_1(&s.into());
+    #[cfg(not(all(target_arch = "wasm32", feature = "web")))]
+    println!("{}", s);
+}
+
+use crate::core::calculate::cost::{CostColor, CostModel};
+use crate::core::calculate::solver::{SolverContext, SolverInput, SolverState};
+use crate::core::{
+    calculate::util::{GenerationSettings, JobControl, ProgressSink},
+    preset::{Preset, UnprocessedPreset},
+    validate::validate_preset,
+};
+use ahash::AHasher;
+use pathfinding::prelude::Weights;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+struct ImgDiffWeights {
+    source: Vec<CostColor>,
+    target: Vec<CostColor>,
+    weights: Vec<i64>,
+    sidelen: usize,
+    proximity_importance: i64,
+    model: CostModel,
+}
+
+impl ImgDiffWeights {
+    fn new(input: &SolverInput) -> Self {
+        Self {
+            source: input.source_colors.clone(),
+            target: input.target_colors.clone(),
+            weights: input.weights.clone(),
+            sidelen: input.settings.sidelen as usize,
+            proximity_importance: input.settings.scaled_proximity_importance(),
+            model: input.settings.cost_model,
+        }
+    }
+
+    /// Upper bound for the cost of any pair: the most different colors in opposite corners.
+    fn max_cost(&self) -> i64 {
+        let far = self.sidelen.saturating_sub(1) as i64;
+        let color = cost::max_color_distance(self.source.iter().chain(&self.target));
+        let weight = self.weights.iter().copied().max().unwrap_or(0);
+        color
+            .saturating_mul(weight)
+            .saturating_add(
+                self.model
+                    .spatial_cost(2 * far * far, self.proximity_importance),
+            )
+            .min(cost::MAX_COST)
+    }
+}
+
+impl ImgDiffWeights {
+    /// Completes a partial assignment for a solver told to finish early: every unassigned row,
+    /// in order, takes the cheapest column that is still free.
+    fn complete_greedily(&self, assigned: &[Option<usize>]) -> Vec<usize> {
+        let mut taken = vec![false; self.columns()];
+        for &col in assigned.iter().flatten() {
+            taken[col] = true;
+        }
+        assigned
+            .iter()
+            .enumerate()
+            .map(|(row, col)| {
+                col.unwrap_or_else(|| {
+                    let col = self.cheapest_free(&taken, row);
+                    taken[col] = true;
+                    col
+                })
+            })
+            .collect()
+    }
+
+    /// The cheapest column for `row` that isn't taken, searching square windows around its
+    /// position until everything outside the window is known to cost more.
+    fn cheapest_free(&self, taken: &[bool], row: usize) -> usize {
+        let sidelen = self.sidelen;
+        let (x, y) = (row % sidelen, row / sidelen);
+        let mut radius = 1;
+        loop {
+            let mut best: Option<(i64, usize)> = None;
+            for sy in y.saturating_sub(radius)..(y + radius + 1).min(sidelen) {
+                for sx in x.saturating_sub(radius)..(x + radius + 1).min(sidelen) {
+                    let col = sy * sidelen + sx;
+                    if taken[col] {
+                        continue;
+                    }
+                    let cost = -self.at(row, col);
+                    if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                        best = Some((cost, col));
+                    }
+                }
+            }
+            if let Some((cost, col)) = best {
+                // anything outside the window is at least radius + 1 away on one axis
+                let outside = self
+                    .model
+                    .spatial_cost((radius as i64 + 1).pow(2), self.proximity_importance);
+                if radius >= sidelen || outside >= cost {
+                    return col;
+                }
+            }
+            radius *= 2;
+        }
+    }
+}
+
+// const TARGET_IMAGE_PATH: &str = "./target.png";
+// const TARGET_WEIGHTS_PATH: &str = "./weights.png";
+
+impl Weights<i64> for ImgDiffWeights {
+    fn rows(&self) -> usize {
+        self.target.len()
+    }
+
+    fn columns(&self) -> usize {
+        self.source.len()
+    }
+
+    #[inline(always)]
+    fn at(&self, row: usize, col: usize) -> i64 {
+        let (x1, y1) = (row % self.sidelen, row / self.sidelen);
+        let (x2, y2) = (col % self.sidelen, col / self.sidelen);
+        let weight = self.weights[row];
+        -self.model.cost(
+            (x1 as u32, y1 as u32),
+            (x2 as u32, y2 as u32),
+            self.target[row],
+            self.source[col],
+            weight,
+            self.proximity_importance,
+        )
+    }
+
+    fn neg(&self) -> Self {
+        todo!()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ProgressMsg {
+    Progress(f32),
+    UpdatePreview {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+    UpdateAssignments(Vec<usize>),
+    Done(Box<Preset>), // result directory
+    Error(CalculateError),
+    Cancelled,
+    /// The job stopped at the user's request; [`resume`] carries on from here.
+    Paused(Box<PausedJob>),
+}
+
+/// A checkpoint of a paused job: everything needed to resume it, even in a later session.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PausedJob {
+    /// The source image, already cropped and scaled to the job's resolution.
+    pub source: UnprocessedPreset,
+    /// The job's settings, with the source crop reset since `source` is already cropped.
+    pub settings: GenerationSettings,
+    pub state: SolverState,
+}
+
+/// A [`ProgressMsg`] tagged with the job that sent it, so messages from a cancelled or
+/// superseded job can be told apart from the current one. Messages that don't belong to any job
+/// use the nil id.
+#[derive(Serialize, Deserialize)]
+pub struct JobMsg {
+    pub job_id: Uuid,
+    pub msg: ProgressMsg,
+}
+
+impl ProgressMsg {
+    pub fn typ(&self) -> &'static str {
+        match self {
+            ProgressMsg::Progress(_) => "progress",
+            ProgressMsg::UpdatePreview { .. } => "update_preview",
+            ProgressMsg::UpdateAssignments(_) => "update_assignments",
+            ProgressMsg::Done(_) => "done",
+            ProgressMsg::Error(_) => "error",
+            ProgressMsg::Cancelled => "cancelled",
+            ProgressMsg::Paused(_) => "paused",
+        }
+    }
+}
+
+type FxIndexSet<K> = indexmap::IndexSet<K, std::hash::BuildHasherDefault<AHasher>>;
+
+fn make_new_img(source_pixels: &[(u8, u8, u8)], assignments: &[usize], sidelen: u32) -> Vec<u8> {
+    let mut img = vec![0; (sidelen * sidelen * 3) as usize];
+    for (target_idx, source_idx) in assignments.iter().enumerate() {
+        let (r, g, b) = source_pixels[*source_idx];
+        let base = target_idx * 3;
+        img[base] = r;
+        img[base + 1] = g;
+        img[base + 2] = b;
+    }
+    img
+}
+
+#[derive(Clone, Copy)]
+struct Pixel {
+    src_x: u32,
+    src_y: u32,
+    color: CostColor,
+    h: i64, // current heuristic value
+}
+
+impl Pixel {
+    fn new(src_x: u32, src_y: u32, color: CostColor, h: i64) -> Self {
+        Self {
+            src_x,
+            src_y,
+            color,
+            h,
+        }
+    }
+
+    fn update_heuristic(&mut self, new_h: i64) {
+        self.h = new_h;
+    }
+
+    #[inline(always)]
+    fn calc_heuristic(
+        &self,
+        model: &CostModel,
+        target_pos: (u32, u32),
+        target_col: CostColor,
+        weight: i64,
+        proximity_importance: i64,
+    ) -> i64 {
+        model.cost(
+            (self.src_x, self.src_y),
+            target_pos,
+            self.color,
+            target_col,
+            weight,
+            proximity_importance,
+        )
+    }
+}
+
+const SWAPS_PER_GENERATION_PER_PIXEL: usize = 128;
+
+// fn serialize_assignments(assignments: Vec<usize>) -> String {
+//     format!(
+//         "[{}]",
+//         assignments
+//             .iter()
+//             .map(|a| a.to_string())
+//             .collect::<Vec<_>>()
+//             .join(",")
+//     )
+// }
+/// Runs a job on the current thread until it finishes or `control` stops it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn process<S: ProgressSink>(
+    unprocessed: UnprocessedPreset,
+    settings: GenerationSettings,
+    tx: &mut S,
+    control: Arc<JobControl>,
+) -> Result<(), CalculateError> {
+    util::block_on(process_async(unprocessed, settings, tx, control))
+}
+
+/// Like [`process`], but suspends between steps so the web worker can handle a cancel request
+/// while the job runs.
+pub async fn process_async<S: ProgressSink>(
+    unprocessed: UnprocessedPreset,
+    settings: GenerationSettings,
+    tx: &mut S,
+    control: Arc<JobControl>,
+) -> Result<(), CalculateError> {
+    run_job(unprocessed, settings, None, None, tx, control).await
+}
+
+/// Runs a job that continues optimizing `preset` with `settings`, which may use a different
+/// proximity importance or solver, instead of starting from scratch.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn refine<S: ProgressSink>(
+    preset: Preset,
+    settings: GenerationSettings,
+    tx: &mut S,
+    control: Arc<JobControl>,
+) -> Result<(), CalculateError> {
+    util::block_on(refine_async(preset, settings, tx, control))
+}
+
+/// Like [`refine`], but suspends between steps like [`process_async`].
+///
+/// The preset's source image is already cropped and scaled, so `settings` must keep its
+/// resolution and the source crop is ignored. The solver must support warm starts.
+pub async fn refine_async<S: ProgressSink>(
+    preset: Preset,
+    mut settings: GenerationSettings,
+    tx: &mut S,
+    control: Arc<JobControl>,
+) -> Result<(), CalculateError> {
+    validate_preset(&preset)?;
+    if settings.sidelen != preset.inner.width || settings.sidelen != preset.inner.height {
+        return Err(CalculateError::InvalidSettings(format!(
+            "a {}×{} preset can only be refined at its own resolution",
+            preset.inner.width, preset.inner.height
+        )));
+    }
+    let solver = solver::solver(settings.algorithm);
+    if !solver.warm_starts() {
+        return Err(CalculateError::InvalidSettings(format!(
+            "the {} can't refine an existing result",
+            solver.label()
+        )));
+    }
+    settings.source_crop_scale = util::CropScale::identity();
+    run_job(
+        preset.inner,
+        settings,
+        Some(preset.assignments),
+        None,
+        tx,
+        control,
+    )
+    .await
+}
+
+/// Carries on with a job that was stopped with [`ProgressMsg::Paused`].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn resume<S: ProgressSink>(
+    job: PausedJob,
+    tx: &mut S,
+    control: Arc<JobControl>,
+) -> Result<(), CalculateError> {
+    util::block_on(resume_async(job, tx, control))
+}
+
+/// Like [`resume`], but suspends between steps like [`process_async`].
+pub async fn resume_async<S: ProgressSink>(
+    job: PausedJob,
+    tx: &mut S,
+    control: Arc<JobControl>,
+) -> Result<(), CalculateError> {
+    let sidelen = job.settings.sidelen;
+    if job.source.width != sidelen || job.source.height != sidelen {
+        return Err(CalculateError::InvalidSettings(format!(
+            "the paused job's source is {}×{}, but its resolution is {sidelen}",
+            job.source.width, job.source.height
+        )));
+    }
+    let solver = solver::solver(job.settings.algorithm);
+    if !solver.pauses() {
+        return Err(CalculateError::InvalidSettings(format!(
+            "the {} can't resume a paused job",
+            solver.label()
+        )));
+    }
+    run_job(job.source, job.settings, None, Some(job.state), tx, control).await
+}
+
+async fn run_job<S: ProgressSink>(
+    unprocessed: UnprocessedPreset,
+    settings: GenerationSettings,
+    initial: Option<Vec<usize>>,
+    resume: Option<SolverState>,
+    tx: &mut S,
+    control: Arc<JobControl>,
+) -> Result<(), CalculateError> {
+    settings.cost_model.check()?;
+    let source_img = util::source_image(&unprocessed)?;
+    let (source, target, weights) = util::get_images(source_img, &settings)?;
+    let input = SolverInput {
+        source_colors: settings.cost_model.colors(&source),
+        target_colors: settings.cost_model.colors(&target),
+        source,
+        target,
+        weights,
+        settings,
+        initial,
+        resume,
+    };
+
+    let solver = solver::solver(input.settings.algorithm);
+    let mut ctx = SolverContext::new(tx, control, input.settings.time_budget);
+    let result = solver.solve(&input, &mut ctx).await;
+    let paused = ctx.take_paused();
+
+    let sidelen = input.settings.sidelen;
+    let source = UnprocessedPreset {
+        name: unprocessed.name,
+        width: sidelen,
+        height: sidelen,
+        source_img: input
+            .source
+            .into_iter()
+            .flat_map(|(r, g, b)| [r, g, b])
+            .collect(),
+    };
+    let assignments = match (result, paused) {
+        (Err(CalculateError::Paused), Some(state)) => {
+            let mut settings = input.settings;
+            settings.source_crop_scale = util::CropScale::identity();
+            tx.send(ProgressMsg::Paused(Box::new(PausedJob {
+                source,
+                settings,
+                state,
+            })));
+            return Err(CalculateError::Paused);
+        }
+        (result, _) => result?,
+    };
+
+    tx.send(ProgressMsg::Done(Box::new(Preset {
+        inner: source,
+        assignments,
+        settings: Some(input.settings),
+    })));
+    Ok(())
+}
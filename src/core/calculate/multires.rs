@@ -0,0 +1,231 @@
+use image::imageops;
+
+use crate::core::calculate::cost::{CostColor, CostModel};
+use crate::core::calculate::genetic::{self, Grid};
+use crate::core::calculate::solver::{AssignmentSolver, SolveFuture, SolverContext, SolverInput};
+use crate::core::calculate::util::Algorithm;
+use crate::core::calculate::{
+    CalculateError, ImgDiffWeights, SWAPS_PER_GENERATION_PER_PIXEL, auction,
+};
+
+/// Coarse to fine: solves a scaled-down copy of the images with the auction algorithm, then
+/// doubles the resolution step by step, using the upsampled result as the starting point for
+/// short runs of local swaps.
+pub(crate) struct Multires;
+
+impl AssignmentSolver for Multires {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::new("Multires")
+    }
+
+    fn name(&self) -> &'static str {
+        "multires"
+    }
+
+    fn label(&self) -> &'static str {
+        "coarse to fine"
+    }
+
+    fn description(&self) -> &'static str {
+        "solves at a low resolution first and refines from there, best for 256 and above"
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+/// Images at most this wide are solved directly.
+const COARSEST_SIDELEN: u32 = 32;
+/// How far pixels may move while refining a level; the upsampled guess is rarely off by more.
+const REFINE_RADIUS: u32 = 6;
+/// Refining a level stops once fewer than one in this many pixels moved in a generation.
+const CONVERGED_PIXELS_PER_SWAP: usize = 1000;
+/// Refining a level stops after this many generations even if it's still improving.
+const MAX_REFINE_GENERATIONS: usize = 64;
+
+/// The images scaled down to one level of the pyramid.
+struct Level {
+    sidelen: u32,
+    /// The source in RGB, for previews.
+    source: Vec<(u8, u8, u8)>,
+    source_colors: Vec<CostColor>,
+    target_colors: Vec<CostColor>,
+    weights: Vec<i64>,
+    proximity_importance: i64,
+    model: CostModel,
+}
+
+impl Level {
+    fn new(input: &SolverInput, sidelen: u32) -> Self {
+        let from = input.settings.sidelen;
+        let model = input.settings.cost_model;
+        let source = scale_colors(&input.source, from, sidelen);
+        let target = scale_colors(&input.target, from, sidelen);
+        Self {
+            sidelen,
+            source_colors: model.colors(&source),
+            target_colors: model.colors(&target),
+            source,
+            weights: scale_weights(&input.weights, from, sidelen),
+            proximity_importance: input.settings.proximity_importance_at(sidelen),
+            model,
+        }
+    }
+
+    fn grid(&self) -> Grid<'_> {
+        Grid {
+            source: &self.source_colors,
+            target: &self.target_colors,
+            weights: &self.weights,
+            sidelen: self.sidelen,
+            proximity_importance: self.proximity_importance,
+            model: self.model,
+        }
+    }
+}
+
+/// The side lengths to solve at, coarsest first, ending with `sidelen` itself.
+fn level_sidelens(sidelen: u32) -> Vec<u32> {
+    let mut sidelens = vec![sidelen];
+    let mut s = sidelen;
+    while s > COARSEST_SIDELEN {
+        s = s.div_ceil(2);
+        sidelens.push(s);
+    }
+    sidelens.reverse();
+    sidelens
+}
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    let sidelens = level_sidelens(input.settings.sidelen);
+    // every level costs roughly in proportion to its pixel count
+    let total_work = sidelens.iter().map(|&s| (s * s) as f32).sum::<f32>();
+    let mut work_done = 0.0;
+
+    let coarse = Level::new(input, sidelens[0]);
+    let weights = ImgDiffWeights {
+        source: coarse.source_colors.clone(),
+        target: coarse.target_colors.clone(),
+        weights: coarse.weights.clone(),
+        sidelen: coarse.sidelen as usize,
+        proximity_importance: coarse.proximity_importance,
+        model: coarse.model,
+    };
+    let level_work = (coarse.sidelen * coarse.sidelen) as f32;
+    let mut assignments = auction::auction(&weights, ctx, |ctx, progress, assigned| {
+        ctx.progress(progress * level_work / total_work);
+        ctx.preview_scaled(
+            &coarse.source,
+            coarse.sidelen,
+            &assigned.iter().map(|a| a.unwrap_or(0)).collect::<Vec<_>>(),
+        );
+    })
+    .await?;
+    work_done += level_work;
+
+    let mut rng = frand::Rand::with_seed(12345);
+    for pair in sidelens.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let level = Level::new(input, to);
+        let grid = level.grid();
+        let mut pixels = grid.place(&upsample(&assignments, from, to));
+
+        let swaps_per_generation = SWAPS_PER_GENERATION_PER_PIXEL * pixels.len();
+        let level_work = (to * to) as f32;
+        let mut max_dist = REFINE_RADIUS;
+        for generation in 0..MAX_REFINE_GENERATIONS {
+            // finishing early still carries the result up to the full resolution
+            if ctx.should_finish() {
+                break;
+            }
+            let swaps_made = grid.swap_pass(&mut pixels, max_dist, swaps_per_generation, &mut rng);
+            ctx.checkpoint().await?;
+
+            let level_progress = generation as f32 / MAX_REFINE_GENERATIONS as f32;
+            ctx.progress((work_done + level_progress * level_work) / total_work);
+            ctx.preview_scaled(&level.source, to, &genetic::assignments(&pixels, to));
+
+            if max_dist == 2 && swaps_made * CONVERGED_PIXELS_PER_SWAP < pixels.len() {
+                break;
+            }
+            max_dist = (max_dist - 1).max(2);
+        }
+        assignments = genetic::assignments(&pixels, to);
+        work_done += level_work;
+    }
+    Ok(assignments)
+}
+
+/// Carries `assignments` for `from`×`from` images over to `to`×`to`: every fine target pixel
+/// takes the pixel at the same offset within the coarse source pixel its coarse target pixel
+/// was assigned. Where the scale isn't exactly 2 the blocks differ in size, so a few pixels
+/// collide; those are handed the unclaimed source pixels in reading order.
+fn upsample(assignments: &[usize], from: u32, to: u32) -> Vec<usize> {
+    let (from, to) = (from as usize, to as usize);
+    // the coarse pixel containing fine coordinate `x`, and the first fine coordinate of coarse `c`
+    let coarse = |x: usize| x * from / to;
+    let block_start = |c: usize| (c * to).div_ceil(from);
+
+    let mut result = vec![usize::MAX; to * to];
+    let mut claimed = vec![false; to * to];
+    for y in 0..to {
+        for x in 0..to {
+            let (cx, cy) = (coarse(x), coarse(y));
+            let src = assignments[cy * from + cx];
+            let (sx, sy) = (src % from, src / from);
+            // same offset within the block, kept inside the source block
+            let fx = (block_start(sx) + x - block_start(cx)).min(block_start(sx + 1) - 1);
+            let fy = (block_start(sy) + y - block_start(cy)).min(block_start(sy + 1) - 1);
+            let fine = fy * to + fx;
+            if !claimed[fine] {
+                claimed[fine] = true;
+                result[y * to + x] = fine;
+            }
+        }
+    }
+
+    let mut unclaimed = (0..to * to).filter(|&i| !claimed[i]);
+    for assignment in result.iter_mut().filter(|a| **a == usize::MAX) {
+        *assignment = unclaimed
+            .next()
+            .expect("as many target pixels as source pixels");
+    }
+    result
+}
+
+fn scale_colors(pixels: &[(u8, u8, u8)], from: u32, to: u32) -> Vec<(u8, u8, u8)> {
+    if from == to {
+        return pixels.to_vec();
+    }
+    let img: image::RgbImage = image::ImageBuffer::from_vec(
+        from,
+        from,
+        pixels.iter().flat_map(|&(r, g, b)| [r, g, b]).collect(),
+    )
+    .expect("pixels match the side length");
+    imageops::resize(&img, to, to, imageops::FilterType::Triangle)
+        .pixels()
+        .map(|p| (p[0], p[1], p[2]))
+        .collect()
+}
+
+fn scale_weights(weights: &[i64], from: u32, to: u32) -> Vec<i64> {
+    if from == to {
+        return weights.to_vec();
+    }
+    let img: image::ImageBuffer<image::Luma<f32>, Vec<f32>> =
+        image::ImageBuffer::from_vec(from, from, weights.iter().map(|&w| w as f32).collect())
+            .expect("weights match the side length");
+    imageops::resize(&img, to, to, imageops::FilterType::Triangle)
+        .pixels()
+        .map(|p| p[0].round() as i64)
+        .collect()
+}
@@ -0,0 +1,467 @@
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread::JoinHandle;
+
+use pathfinding::prelude::Weights;
+
+use crate::core::calculate::solver::{
+    AssignmentSolver, SolveFuture, SolverContext, SolverInput, SolverState,
+};
+use crate::core::calculate::util::{Algorithm, thread_count};
+use crate::core::calculate::{CalculateError, FxIndexSet, ImgDiffWeights};
+
+/// Kuhn-Munkres: the assignment with the lowest total cost, in O(n³).
+pub(crate) struct Optimal;
+
+impl AssignmentSolver for Optimal {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::OPTIMAL
+    }
+
+    fn name(&self) -> &'static str {
+        "optimal"
+    }
+
+    fn label(&self) -> &'static str {
+        "optimal algorithm"
+    }
+
+    fn description(&self) -> &'static str {
+        "finds the best possible result, but takes a long time at high resolutions"
+    }
+
+    fn pauses(&self) -> bool {
+        true
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+/// Below this many columns the per-column loops aren't worth splitting over threads.
+const PARALLEL_MIN_COLUMNS: usize = 2048;
+
+/// The labels and the alternating tree of the search from one root.
+struct Search {
+    /// lx is the labelling for x nodes, ly the labelling for y nodes.
+    lx: Vec<i64>,
+    ly: Vec<i64>,
+    /// The x nodes in the alternating path.
+    s: FxIndexSet<usize>,
+    /// Some(prev) when the corresponding y node belongs to the alternating path.
+    alternating: Vec<Option<usize>>,
+    slack: Vec<i64>,
+    slackx: Vec<usize>,
+}
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    let weights = Arc::new(ImgDiffWeights::new(input));
+    let threads = thread_count();
+
+    // pathfinding::kuhn_munkres, inlined to allow for progress bar and cancelling
+    let (_total_diff, assignments) = {
+        // We call x the rows and y the columns. (nx, ny) is the size of the matrix.
+        let nx = weights.rows();
+        let ny = weights.columns();
+        assert!(
+            nx <= ny,
+            "number of rows must not be larger than number of columns"
+        );
+        // xy represents matching for x, yz matching for y
+        let (first_root, mut xy, lx, ly) = match &input.resume {
+            Some(state) => resumed(state, nx, ny).ok_or_else(SolverState::mismatch)?,
+            // We start with an acceptable labelling with the maximum possible values for lx
+            // and 0 for ly.
+            None => (
+                0,
+                vec![None; nx],
+                (0..nx)
+                    .map(|row| (0..ny).map(|col| weights.at(row, col)).max().unwrap())
+                    .collect::<Vec<_>>(),
+                vec![0; ny],
+            ),
+        };
+        let mut yx: Vec<Option<usize>> = vec![None; ny];
+        for (x, y) in xy.iter().enumerate() {
+            if let Some(y) = *y {
+                yx[y] = Some(x);
+            }
+        }
+        let mut search = Search {
+            lx,
+            ly,
+            // s, alternating, and slack will be reset every time they are reused.
+            s: FxIndexSet::<usize>::default(),
+            alternating: Vec::with_capacity(ny),
+            slack: vec![0; ny],
+            slackx: vec![0; ny],
+        };
+        // started once for the whole solve; always 1 thread in the browser
+        let mut workers = (threads > 1 && ny >= PARALLEL_MIN_COLUMNS)
+            .then(|| Workers::start(&weights, &search.ly, threads));
+        for root in first_root..nx {
+            search.alternating.clear();
+            search.alternating.resize(ny, None);
+            search.s.clear();
+            search.s.insert(root);
+            // Find y such that the path is augmented.
+            let found = match &workers {
+                Some(workers) => search.augmenting_path_parallel(workers, &yx, root),
+                None => search.augmenting_path(&weights, &yx, root),
+            };
+            let mut y = Some(found);
+            // Inverse edges along the augmenting path.
+            while y.is_some() {
+                let x = search.alternating[y.unwrap()].unwrap();
+                let prec = xy[x];
+                yx[y.unwrap()] = Some(x);
+                xy[x] = y;
+                y = prec;
+            }
+            ctx.checkpoint().await?;
+            if ctx.should_finish() {
+                return Ok(weights.complete_greedily(&xy));
+            }
+            if ctx.should_pause() {
+                if let Some(mut workers) = workers.take() {
+                    search.ly = workers.stop();
+                }
+                return Err(ctx.pause(SolverState::Optimal {
+                    next_root: root + 1,
+                    lx: search.lx,
+                    ly: search.ly,
+                    matching: xy,
+                }));
+            }
+            if root % 100 == 0 {
+                // send progress
+                ctx.progress(root as f32 / nx as f32);
+                ctx.preview(
+                    input,
+                    &xy.iter().map(|a| a.unwrap_or(0)).collect::<Vec<_>>(),
+                );
+            }
+        }
+        if let Some(mut workers) = workers.take() {
+            search.ly = workers.stop();
+        }
+        (
+            search.lx.into_iter().sum::<i64>() + search.ly.into_iter().sum::<i64>(),
+            xy.into_iter().map(Option::unwrap).collect::<Vec<_>>(),
+        )
+    };
+
+    Ok(assignments)
+}
+
+/// The first row still to match, the matching and the labels from a saved state, if it is one
+/// of ours and fits an `nx` by `ny` matrix: the first rows are matched to distinct columns and
+/// the rest aren't matched yet.
+#[allow(clippy::type_complexity)]
+fn resumed(
+    state: &SolverState,
+    nx: usize,
+    ny: usize,
+) -> Option<(usize, Vec<Option<usize>>, Vec<i64>, Vec<i64>)> {
+    let SolverState::Optimal {
+        next_root,
+        lx,
+        ly,
+        matching,
+    } = state
+    else {
+        return None;
+    };
+    if lx.len() != nx || ly.len() != ny || matching.len() != nx || *next_root > nx {
+        return None;
+    }
+    let mut taken = vec![false; ny];
+    for (x, y) in matching.iter().enumerate() {
+        match *y {
+            Some(y) if x < *next_root && y < ny && !taken[y] => taken[y] = true,
+            None if x >= *next_root => {}
+            _ => return None,
+        }
+    }
+    Some((*next_root, matching.clone(), lx.clone(), ly.clone()))
+}
+
+impl Search {
+    /// Grows the alternating tree from `root` until it reaches a free y node, and returns it.
+    fn augmenting_path(
+        &mut self,
+        weights: &ImgDiffWeights,
+        yx: &[Option<usize>],
+        root: usize,
+    ) -> usize {
+        let Search {
+            lx,
+            ly,
+            s,
+            alternating,
+            slack,
+            slackx,
+        } = self;
+        let ny = ly.len();
+        // Slack for a vertex y is, initially, the margin between the
+        // sum of the labels of root and y, and the weight between root and y.
+        // As we add x nodes to the alternating path, we update the slack to
+        // represent the smallest margin between one of the x nodes and y.
+        for y in 0..ny {
+            slack[y] = lx[root] + ly[y] - weights.at(root, y);
+        }
+        slackx.fill(root);
+        loop {
+            let mut delta = pathfinding::num_traits::Bounded::max_value();
+            let mut x = 0;
+            let mut y = 0;
+            // Select one of the smallest slack delta and its edge (x, y)
+            // for y not in the alternating path already.
+            for yy in 0..ny {
+                if alternating[yy].is_none() && slack[yy] < delta {
+                    delta = slack[yy];
+                    x = slackx[yy];
+                    y = yy;
+                }
+            }
+            // If some slack has been found, remove it from x nodes in the
+            // alternating path, and add it to y nodes in the alternating path.
+            // The slack of y nodes outside the alternating path will be reduced
+            // by this minimal slack as well.
+            if delta > 0 {
+                for &x in s.iter() {
+                    lx[x] -= delta;
+                }
+                for y in 0..ny {
+                    if alternating[y].is_some() {
+                        ly[y] += delta;
+                    } else {
+                        slack[y] -= delta;
+                    }
+                }
+            }
+            // Add (x, y) to the alternating path.
+            alternating[y] = Some(x);
+            if yx[y].is_none() {
+                // We have found an augmenting path.
+                return y;
+            }
+            // This y node had a predecessor, add it to the set of x nodes
+            // in the augmenting path.
+            let x = yx[y].unwrap();
+            s.insert(x);
+            // Update slack because of the added vertex in s might contain a
+            // greater slack than with previously inserted x nodes in the augmenting
+            // path.
+            for y in 0..ny {
+                if alternating[y].is_none() {
+                    let alternate_slack = lx[x] + ly[y] - weights.at(x, y);
+                    if slack[y] > alternate_slack {
+                        slack[y] = alternate_slack;
+                        slackx[y] = x;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Same as [`Self::augmenting_path`] with identical results, but the loops over y nodes are
+    /// run by `workers`, which hold the y labels and slacks in the meantime. The calling thread
+    /// combines the smallest slack of each worker's range in order.
+    fn augmenting_path_parallel(
+        &mut self,
+        workers: &Workers,
+        yx: &[Option<usize>],
+        root: usize,
+    ) -> usize {
+        let mut min = workers.run(Command::Start {
+            root,
+            lx_root: self.lx[root],
+        });
+        loop {
+            let (delta, x, y) = min;
+            if delta > 0 {
+                for &x in self.s.iter() {
+                    self.lx[x] -= delta;
+                }
+            }
+            self.alternating[y] = Some(x);
+            let next = yx[y].map(|next| {
+                self.s.insert(next);
+                (next, self.lx[next])
+            });
+            min = workers.run(Command::Step {
+                delta,
+                added: y,
+                next,
+            });
+            if next.is_none() {
+                return y;
+            }
+        }
+    }
+}
+
+/// What the workers do next.
+#[derive(Clone, Copy)]
+enum Command {
+    /// Start the slacks of a search from `root`, whose label is `lx_root`.
+    Start { root: usize, lx_root: i64 },
+    Step {
+        delta: i64,
+        /// The y node joining the alternating path.
+        added: usize,
+        /// The x node behind it and its label, or `None` if the path is complete.
+        next: Option<(usize, i64)>,
+    },
+    /// Hand back the y labels and exit.
+    Stop,
+}
+
+/// The smallest slack in a range: (delta, x, y).
+type Min = (i64, usize, usize);
+
+/// What the solving thread and the workers share.
+struct Shared {
+    command: Mutex<Command>,
+    results: Vec<Mutex<Min>>,
+    barrier: Barrier,
+}
+
+/// Threads that each own a contiguous range of the y nodes for a whole solve. They do one step
+/// per command: apply the previous delta, add the new x node's slack and report the smallest
+/// slack in their range.
+struct Workers {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<Vec<i64>>>,
+}
+
+impl Workers {
+    fn start(weights: &Arc<ImgDiffWeights>, ly: &[i64], threads: usize) -> Self {
+        let chunk = ly.len().div_ceil(threads);
+        let ranges = ly.chunks(chunk).map(<[i64]>::to_vec).collect::<Vec<_>>();
+        let shared = Arc::new(Shared {
+            command: Mutex::new(Command::Stop),
+            results: (0..ranges.len())
+                .map(|_| Mutex::new((i64::MAX, 0, 0)))
+                .collect(),
+            barrier: Barrier::new(ranges.len() + 1),
+        });
+        let threads = ranges
+            .into_iter()
+            .enumerate()
+            .map(|(i, ly)| {
+                let (shared, weights) = (shared.clone(), weights.clone());
+                std::thread::spawn(move || work(&shared, &weights, i, i * chunk, ly))
+            })
+            .collect();
+        Self { shared, threads }
+    }
+
+    /// Has every worker run `command`, and returns the smallest slack outside the alternating
+    /// path.
+    fn run(&self, command: Command) -> Min {
+        *self.shared.command.lock().unwrap() = command;
+        // let the workers run the command, then wait for their minimums
+        self.shared.barrier.wait();
+        self.shared.barrier.wait();
+        let mut min: Min = (i64::MAX, 0, 0);
+        for result in &self.shared.results {
+            let local = *result.lock().unwrap();
+            if local.0 < min.0 {
+                min = local;
+            }
+        }
+        min
+    }
+
+    /// Stops the workers and returns the y labels, or nothing if they were already stopped.
+    fn stop(&mut self) -> Vec<i64> {
+        if self.threads.is_empty() {
+            return Vec::new();
+        }
+        *self.shared.command.lock().unwrap() = Command::Stop;
+        self.shared.barrier.wait();
+        self.threads
+            .drain(..)
+            .flat_map(|thread| thread.join().unwrap())
+            .collect()
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A worker's loop over the y nodes from `offset` on, whose labels start out as `ly`.
+fn work(
+    shared: &Shared,
+    weights: &ImgDiffWeights,
+    index: usize,
+    offset: usize,
+    mut ly: Vec<i64>,
+) -> Vec<i64> {
+    let mut slack = vec![0; ly.len()];
+    let mut slackx = vec![0; ly.len()];
+    // whether the y node is in the alternating path
+    let mut alternating = vec![false; ly.len()];
+    loop {
+        shared.barrier.wait();
+        let command = *shared.command.lock().unwrap();
+        let next = match command {
+            Command::Stop => return ly,
+            Command::Start { root, lx_root } => {
+                alternating.fill(false);
+                for (j, slack) in slack.iter_mut().enumerate() {
+                    *slack = lx_root + ly[j] - weights.at(root, offset + j);
+                }
+                slackx.fill(root);
+                None
+            }
+            Command::Step { delta, added, next } => {
+                if delta > 0 {
+                    for j in 0..ly.len() {
+                        if alternating[j] {
+                            ly[j] += delta;
+                        } else {
+                            slack[j] -= delta;
+                        }
+                    }
+                }
+                if (offset..offset + ly.len()).contains(&added) {
+                    alternating[added - offset] = true;
+                }
+                next
+            }
+        };
+        if let Some((x, lx_x)) = next {
+            for j in 0..ly.len() {
+                if !alternating[j] {
+                    let alternate_slack = lx_x + ly[j] - weights.at(x, offset + j);
+                    if slack[j] > alternate_slack {
+                        slack[j] = alternate_slack;
+                        slackx[j] = x;
+                    }
+                }
+            }
+        }
+
+        let mut min: Min = (i64::MAX, 0, 0);
+        for j in 0..ly.len() {
+            if !alternating[j] && slack[j] < min.0 {
+                min = (slack[j], slackx[j], offset + j);
+            }
+        }
+        *shared.results[index].lock().unwrap() = min;
+        shared.barrier.wait();
+    }
+}
@@ -0,0 +1,169 @@
+use crate::core::calculate::genetic::{self, Grid, Run, Tile};
+use crate::core::calculate::solver::{AssignmentSolver, SolveFuture, SolverContext, SolverInput};
+use crate::core::calculate::util::{Algorithm, thread_count};
+use crate::core::calculate::{CalculateError, Pixel, SWAPS_PER_GENERATION_PER_PIXEL};
+
+/// The genetic solver spread over every core: each generation the grid is cut into tiles that
+/// are swapped in independently, and the tile grid shifts by half a tile every other generation
+/// so pixels can cross the borders. Every tile has its own random numbers, so the result doesn't
+/// depend on the number of threads. In the browser the tiles are worked on one after another.
+pub(crate) struct ParallelGenetic;
+
+impl AssignmentSolver for ParallelGenetic {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::new("ParallelGenetic")
+    }
+
+    fn name(&self) -> &'static str {
+        "parallel"
+    }
+
+    fn label(&self) -> &'static str {
+        "fast algorithm (multithreaded)"
+    }
+
+    fn description(&self) -> &'static str {
+        "the fast algorithm on all cores; results differ slightly from the single-threaded one"
+    }
+
+    fn warm_starts(&self) -> bool {
+        true
+    }
+
+    fn pauses(&self) -> bool {
+        true
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+/// Tiles are never narrower than this, however short the swap distance gets.
+const MIN_TILE_SIDE: usize = 16;
+/// The grid is always cut into at least this many tiles across, so even the first generations,
+/// with swaps across the whole grid, keep every core busy. It doesn't depend on the number of
+/// threads, which would change the result.
+const MIN_TILES_ACROSS: usize = 4;
+const SEED: u64 = 12345;
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    let settings = &input.settings;
+    let sidelen = settings.sidelen as usize;
+    let grid = Grid::new(input);
+    let mut run = Run::start(&grid, input)?;
+    let threads = thread_count();
+
+    loop {
+        let (generation, max_dist) = (run.generation, run.max_dist);
+        let side = (2 * max_dist as usize + 1)
+            .max(MIN_TILE_SIDE)
+            .min(sidelen.div_ceil(MIN_TILES_ACROSS));
+        let offset = if generation % 2 == 1 { side / 2 } else { 0 };
+        let mut jobs = tiles(sidelen, side, offset)
+            .into_iter()
+            .enumerate()
+            .map(|(i, tile)| {
+                let seed = SEED ^ (generation << 32) ^ i as u64;
+                (tile, gather(&run.pixels, sidelen, tile), seed, 0)
+            })
+            .collect::<Vec<_>>();
+
+        run_jobs(
+            &mut jobs,
+            threads,
+            |(tile, tile_pixels, seed, swaps_made)| {
+                let swaps = SWAPS_PER_GENERATION_PER_PIXEL * tile_pixels.len();
+                let mut rng = frand::Rand::with_seed(*seed);
+                *swaps_made = grid
+                    .anneal_tile(tile_pixels, *tile, max_dist, swaps, 0.0, &mut rng)
+                    .0;
+            },
+        );
+
+        let mut swaps_made = 0;
+        for (tile, tile_pixels, _, tile_swaps) in &jobs {
+            scatter(&mut run.pixels, sidelen, *tile, tile_pixels);
+            swaps_made += tile_swaps;
+        }
+
+        ctx.checkpoint().await?;
+
+        let assignments = genetic::assignments(&run.pixels, settings.sidelen);
+        if ctx.should_finish() || (max_dist < 4 && swaps_made < 10) {
+            return Ok(assignments);
+        }
+        ctx.preview(input, &assignments);
+        ctx.progress(1.0 - max_dist as f32 / run.first_dist as f32);
+
+        run.max_dist = genetic::next_dist(max_dist);
+        run.generation += 1;
+        if ctx.should_pause() {
+            return Err(ctx.pause(run.paused(settings.sidelen)));
+        }
+    }
+}
+
+/// Tiles `side` wide covering the grid, with the first row and column `offset` wide if it isn't
+/// zero.
+fn tiles(sidelen: usize, side: usize, offset: usize) -> Vec<Tile> {
+    let mut starts = vec![0];
+    let mut start = if offset > 0 { offset } else { side };
+    while start < sidelen {
+        starts.push(start);
+        start += side;
+    }
+    let spans = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| (start, starts.get(i + 1).copied().unwrap_or(sidelen) - start))
+        .collect::<Vec<_>>();
+
+    let mut tiles = Vec::with_capacity(spans.len() * spans.len());
+    for &(y, height) in &spans {
+        for &(x, width) in &spans {
+            tiles.push(Tile {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+    }
+    tiles
+}
+
+fn gather(pixels: &[Pixel], sidelen: usize, tile: Tile) -> Vec<Pixel> {
+    (tile.y..tile.y + tile.height)
+        .flat_map(|y| &pixels[y * sidelen + tile.x..y * sidelen + tile.x + tile.width])
+        .copied()
+        .collect()
+}
+
+fn scatter(pixels: &mut [Pixel], sidelen: usize, tile: Tile, tile_pixels: &[Pixel]) {
+    for (row, y) in tile_pixels.chunks_exact(tile.width).zip(tile.y..) {
+        pixels[y * sidelen + tile.x..y * sidelen + tile.x + tile.width].copy_from_slice(row);
+    }
+}
+
+/// Calls `work` on every job, on up to `threads` threads.
+fn run_jobs<T: Send>(jobs: &mut [T], threads: usize, work: impl Fn(&mut T) + Sync) {
+    if threads <= 1 || jobs.len() <= 1 {
+        jobs.iter_mut().for_each(work);
+        return;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::scope(|scope| {
+        for chunk in jobs.chunks_mut(jobs.len().div_ceil(threads)) {
+            let work = &work;
+            scope.spawn(move || chunk.iter_mut().for_each(work));
+        }
+    });
+}
@@ -0,0 +1,150 @@
+use crate::core::calculate::cost::CostColor;
+use crate::core::calculate::genetic::{self, Grid};
+use crate::core::calculate::solver::{AssignmentSolver, SolveFuture, SolverContext, SolverInput};
+use crate::core::calculate::util::Algorithm;
+use crate::core::calculate::{CalculateError, SWAPS_PER_GENERATION_PER_PIXEL};
+
+/// Sliced optimal transport: treats every pixel as a point made of its position and its color in
+/// the cost model's color space, and repeatedly projects both images onto a random direction,
+/// sorts them and moves each source point to the target point of the same rank along it. The
+/// points' final positions are sorted into a grid and polished with a few rounds of local swaps.
+/// Each iteration is O(n log n). Per-pixel weights and the spatial exponent are only taken into
+/// account by the polish.
+pub(crate) struct Sliced;
+
+impl AssignmentSolver for Sliced {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::new("Sliced")
+    }
+
+    fn name(&self) -> &'static str {
+        "sliced"
+    }
+
+    fn label(&self) -> &'static str {
+        "sliced transport"
+    }
+
+    fn description(&self) -> &'static str {
+        "a rough result very quickly, even at the highest resolutions"
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+const ITERATIONS: usize = 256;
+/// Iterations between previews, which need a full permutation extraction.
+const ITERATIONS_PER_PREVIEW: usize = 16;
+/// Generations of local swaps after the permutation extraction.
+const POLISH_GENERATIONS: usize = 4;
+/// The proximity importance at which crossing the whole image weighs as much as going from
+/// black to white. Positions always count a little, since the result is read off them.
+const NEUTRAL_PROXIMITY_IMPORTANCE: f32 = 13.0;
+
+type Point = [f32; 5];
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    let sidelen = input.settings.sidelen;
+    let spatial_scale = 255.0 / sidelen as f32
+        * (input.settings.proximity_importance.max(1) as f32 / NEUTRAL_PROXIMITY_IMPORTANCE).sqrt();
+    let mut source = points(&input.source_colors, sidelen, spatial_scale);
+    let target = points(&input.target_colors, sidelen, spatial_scale);
+    let n = source.len();
+
+    let mut rng = frand::Rand::with_seed(12345);
+    let mut source_proj = vec![0.0; n];
+    let mut target_proj = vec![0.0; n];
+    let mut source_order = (0..n).collect::<Vec<_>>();
+    let mut target_order = (0..n).collect::<Vec<_>>();
+    for iteration in 0..ITERATIONS {
+        let dir = random_direction(&mut rng);
+        project(&source, dir, &mut source_proj, &mut source_order);
+        project(&target, dir, &mut target_proj, &mut target_order);
+        for (&s, &t) in source_order.iter().zip(&target_order) {
+            let shift = target_proj[t] - source_proj[s];
+            for (c, d) in source[s].iter_mut().zip(dir) {
+                *c += shift * d;
+            }
+        }
+
+        ctx.checkpoint().await?;
+        if ctx.should_finish() {
+            break;
+        }
+        ctx.progress(iteration as f32 / ITERATIONS as f32);
+        if iteration % ITERATIONS_PER_PREVIEW == 0 {
+            ctx.preview(input, &extract(&source, sidelen));
+        }
+    }
+
+    let grid = Grid::new(input);
+    let mut pixels = grid.place(&extract(&source, sidelen));
+    for _ in 0..POLISH_GENERATIONS {
+        if ctx.should_finish() {
+            break;
+        }
+        grid.swap_pass(&mut pixels, 2, SWAPS_PER_GENERATION_PER_PIXEL * n, &mut rng);
+        ctx.checkpoint().await?;
+    }
+    Ok(genetic::assignments(&pixels, sidelen))
+}
+
+fn points(pixels: &[CostColor], sidelen: u32, spatial_scale: f32) -> Vec<Point> {
+    pixels
+        .iter()
+        .enumerate()
+        .map(|(i, &[r, g, b])| {
+            let x = (i as u32 % sidelen) as f32;
+            let y = (i as u32 / sidelen) as f32;
+            [
+                x * spatial_scale,
+                y * spatial_scale,
+                r as f32,
+                g as f32,
+                b as f32,
+            ]
+        })
+        .collect()
+}
+
+/// A uniformly distributed unit vector, by rejection sampling from the unit ball.
+fn random_direction(rng: &mut frand::Rand) -> Point {
+    loop {
+        let mut dir = [0.0; 5];
+        for d in &mut dir {
+            *d = rng.gen_range(-1.0..1.0f32);
+        }
+        let len = dir.iter().map(|d| d * d).sum::<f32>().sqrt();
+        if len > 1e-3 && len <= 1.0 {
+            return dir.map(|d| d / len);
+        }
+    }
+}
+
+/// Projects `points` onto `dir` and sorts `order` by the projections.
+fn project(points: &[Point], dir: Point, proj: &mut [f32], order: &mut [usize]) {
+    for (p, point) in proj.iter_mut().zip(points) {
+        *p = point.iter().zip(dir).map(|(c, d)| c * d).sum();
+    }
+    order.sort_unstable_by(|&a, &b| proj[a].total_cmp(&proj[b]));
+}
+
+/// Turns the moved source points into assignments: sorted by y they fill the rows of the grid,
+/// and each row is sorted by x.
+fn extract(points: &[Point], sidelen: u32) -> Vec<usize> {
+    let mut order = (0..points.len()).collect::<Vec<_>>();
+    order.sort_unstable_by(|&a, &b| points[a][1].total_cmp(&points[b][1]));
+    for row in order.chunks_mut(sidelen as usize) {
+        row.sort_unstable_by(|&a, &b| points[a][0].total_cmp(&points[b][0]));
+    }
+    order
+}
@@ -0,0 +1,203 @@
+//! The interface every assignment algorithm implements, and the registry the settings window and
+//! command line pick them from.
+//!
+//! A solver only computes the assignments; loading and scaling the images, cancellation and
+//! sending the finished preset are shared by all of them in [`super::process_async`]. Every
+//! solver must be able to stop early with a complete result when
+//! [`SolverContext::should_finish`] says so. Solvers that can be paused save what they need to
+//! carry on in a [`SolverState`]. To add an
+//! algorithm, list it in [`SOLVERS`] with an [`Algorithm`] id no other solver uses.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::calculate::cost::CostColor;
+use crate::core::calculate::util::{
+    Algorithm, Checkpoint, GenerationSettings, JobControl, ProgressSink,
+};
+use crate::core::calculate::{
+    CalculateError, ProgressMsg, annealing, auction, genetic, make_new_img, multires, optimal,
+    parallel, sliced, sparse,
+};
+
+/// The images a solver matches, already cropped and scaled to `settings.sidelen`.
+pub struct SolverInput {
+    pub source: Vec<(u8, u8, u8)>,
+    pub target: Vec<(u8, u8, u8)>,
+    /// `source` and `target` in the color space of `settings.cost_model`, which is what the
+    /// solvers compare.
+    pub source_colors: Vec<CostColor>,
+    pub target_colors: Vec<CostColor>,
+    /// Importance of each target pixel.
+    pub weights: Vec<i64>,
+    pub settings: GenerationSettings,
+    /// Assignments to continue from when refining an existing result.
+    pub initial: Option<Vec<usize>>,
+    /// The state to carry on from when resuming a paused job.
+    pub resume: Option<SolverState>,
+}
+
+/// What a solver needs to carry on after its job was paused. Each solver reads only its own
+/// variant, and checks it against the input since it may come from a file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SolverState {
+    /// Kuhn-Munkres after matching the first `next_root` rows.
+    Optimal {
+        next_root: usize,
+        lx: Vec<i64>,
+        ly: Vec<i64>,
+        /// The column matched to every row so far.
+        matching: Vec<Option<usize>>,
+    },
+    /// The genetic solvers, which seed their random numbers from the generation so nothing
+    /// else about them needs saving.
+    Genetic {
+        assignments: Vec<usize>,
+        generation: u64,
+        max_dist: u32,
+        first_dist: u32,
+    },
+    Annealing {
+        assignments: Vec<usize>,
+        generation: u64,
+        max_dist: u32,
+        temperature: f64,
+        /// What the temperatures are relative to, from the starting assignment's cost.
+        scale: f64,
+        best: Vec<usize>,
+    },
+}
+
+impl SolverState {
+    /// The error for a state that doesn't belong to the solver or the input it was given to.
+    pub(crate) fn mismatch() -> CalculateError {
+        CalculateError::InvalidSettings(
+            "the paused job's state doesn't match its settings".to_owned(),
+        )
+    }
+}
+
+/// How a running solver reports progress and learns that it should stop or finish.
+pub struct SolverContext<'a> {
+    sink: &'a mut dyn ProgressSink,
+    checkpoint: Checkpoint,
+    paused: Option<SolverState>,
+}
+
+impl<'a> SolverContext<'a> {
+    pub(crate) fn new(
+        sink: &'a mut dyn ProgressSink,
+        control: Arc<JobControl>,
+        time_budget: Option<u32>,
+    ) -> Self {
+        Self {
+            sink,
+            checkpoint: Checkpoint::new(control, time_budget),
+            paused: None,
+        }
+    }
+
+    /// Reports how far along the job is, from 0 to 1.
+    pub fn progress(&mut self, progress: f32) {
+        self.sink.send(ProgressMsg::Progress(progress));
+    }
+
+    /// Sends a preview of the output with the given, possibly incomplete, assignments.
+    pub fn preview(&mut self, input: &SolverInput, assignments: &[usize]) {
+        self.preview_scaled(&input.source, input.settings.sidelen, assignments);
+    }
+
+    /// Like [`Self::preview`], for solvers working on a scaled-down copy of the source image.
+    pub fn preview_scaled(&mut self, source: &[(u8, u8, u8)], sidelen: u32, assignments: &[usize]) {
+        self.sink.send(ProgressMsg::UpdatePreview {
+            width: sidelen,
+            height: sidelen,
+            data: make_new_img(source, assignments, sidelen),
+        });
+    }
+
+    /// Call between steps; returns [`CalculateError::Cancelled`] if the job has been cancelled,
+    /// which the solver should pass on with `?`.
+    pub async fn checkpoint(&mut self) -> Result<(), CalculateError> {
+        self.checkpoint.check(&mut *self.sink).await
+    }
+
+    /// Whether the user asked to finish now or the time budget has run out. Check after
+    /// [`Self::checkpoint`]; the solver should then return the best complete assignments it can
+    /// make from what it has.
+    pub fn should_finish(&self) -> bool {
+        self.checkpoint.should_finish()
+    }
+
+    /// Whether the user asked to pause the job. Solvers that can be paused check after
+    /// [`Self::checkpoint`] and then return the error from [`Self::pause`].
+    pub fn should_pause(&self) -> bool {
+        self.checkpoint.should_pause()
+    }
+
+    /// Keeps `state` to be sent with the paused job, and returns [`CalculateError::Paused`].
+    pub fn pause(&mut self, state: SolverState) -> CalculateError {
+        self.paused = Some(state);
+        CalculateError::Paused
+    }
+
+    pub(crate) fn take_paused(&mut self) -> Option<SolverState> {
+        self.paused.take()
+    }
+}
+
+pub type SolveFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<usize>, CalculateError>> + 'a>>;
+
+pub trait AssignmentSolver: Sync {
+    /// The id settings pick the solver by, unique among [`SOLVERS`].
+    fn algorithm(&self) -> Algorithm;
+    /// Name used on the command line.
+    fn name(&self) -> &'static str;
+    /// Name shown in the settings window.
+    fn label(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    /// Whether the solver continues from [`SolverInput::initial`], so it can refine a result.
+    fn warm_starts(&self) -> bool {
+        false
+    }
+    /// Whether the solver can be paused and resumed from [`SolverInput::resume`].
+    fn pauses(&self) -> bool {
+        false
+    }
+    /// Finds the source pixel for every target pixel. The result must be a permutation of
+    /// `0..input.source.len()`.
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a>;
+}
+
+/// Every available solver, in the order they are offered. Settings can only name the algorithms
+/// of solvers listed here, so nothing else needs to change to add one.
+pub static SOLVERS: &[&dyn AssignmentSolver] = &[
+    &optimal::Optimal,
+    &auction::Auction,
+    &sparse::Sparse,
+    &multires::Multires,
+    &genetic::Genetic,
+    &parallel::ParallelGenetic,
+    &annealing::Annealing,
+    &sliced::Sliced,
+];
+
+/// The solver registered as `algorithm`. Every [`Algorithm`] comes from a registered solver, or
+/// is checked against [`SOLVERS`] when settings are read.
+pub fn solver(algorithm: Algorithm) -> &'static dyn AssignmentSolver {
+    *SOLVERS
+        .iter()
+        .find(|solver| solver.algorithm() == algorithm)
+        .expect("algorithms are only made for registered solvers")
+}
+
+pub fn find_solver(name: &str) -> Option<&'static dyn AssignmentSolver> {
+    SOLVERS.iter().copied().find(|solver| solver.name() == name)
+}
@@ -0,0 +1,349 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use pathfinding::prelude::Weights;
+
+use crate::core::calculate::cost::CostColor;
+use crate::core::calculate::solver::{AssignmentSolver, SolveFuture, SolverContext, SolverInput};
+use crate::core::calculate::util::Algorithm;
+use crate::core::calculate::{CalculateError, ImgDiffWeights};
+
+/// Shortest augmenting paths (Jonker–Volgenant) over a sparse graph that only has the
+/// [`CANDIDATES`] cheapest source pixels of every target pixel. A target pixel whose candidates
+/// are all taken gets twice as many until it fits in, so the result is optimal among assignments
+/// using the pairs the graph ends up with. Every target pixel can also keep its own source pixel,
+/// so the result never costs more than leaving the image as it is.
+pub(crate) struct Sparse;
+
+impl AssignmentSolver for Sparse {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::new("Sparse")
+    }
+
+    fn name(&self) -> &'static str {
+        "sparse"
+    }
+
+    fn label(&self) -> &'static str {
+        "nearest candidates"
+    }
+
+    fn description(&self) -> &'static str {
+        "nearly optimal at high resolutions, unless proximity importance is very low"
+    }
+
+    fn solve<'a>(
+        &'a self,
+        input: &'a SolverInput,
+        ctx: &'a mut SolverContext<'_>,
+    ) -> SolveFuture<'a> {
+        Box::pin(solve(input, ctx))
+    }
+}
+
+/// Source pixels considered for every target pixel.
+const CANDIDATES: usize = 32;
+/// Rows between progress reports and cancellation checks.
+const ROWS_PER_STEP: usize = 256;
+
+/// An edge of the sparse graph: a source pixel and the cost of putting it on the row's target
+/// pixel.
+type Edge = (usize, i64);
+
+async fn solve(
+    input: &SolverInput,
+    ctx: &mut SolverContext<'_>,
+) -> Result<Vec<usize>, CalculateError> {
+    let weights = ImgDiffWeights::new(input);
+    let n = weights.rows();
+    let colors = (weights.proximity_importance == 0).then(|| ColorGrid::new(&weights.source));
+
+    let mut edges = Vec::with_capacity(n);
+    for row in 0..n {
+        edges.push(candidates(
+            &weights,
+            colors.as_ref(),
+            row,
+            CANDIDATES.min(n),
+        ));
+        if row % ROWS_PER_STEP == 0 {
+            ctx.checkpoint().await?;
+        }
+    }
+
+    // column prices; every assigned row is on its cheapest edge at these prices
+    let mut prices = vec![0i64; n];
+    let mut owner: Vec<Option<usize>> = vec![None; n];
+    let mut assigned: Vec<Option<usize>> = vec![None; n];
+    let mut search = Search::new(n);
+    for root in 0..n {
+        while !search.augment(root, &edges, &mut prices, &mut owner, &mut assigned) {
+            // the candidates of the rows reachable from here are all taken
+            let k = edges[root].len();
+            if k >= n {
+                return Err(CalculateError::Internal(format!(
+                    "target pixel {root} can't be assigned with every source pixel as a candidate"
+                )));
+            }
+            // the root is unassigned, so new edges can't break the price invariant
+            edges[root] = candidates(&weights, colors.as_ref(), root, (2 * k).min(n));
+        }
+
+        if root % ROWS_PER_STEP == 0 {
+            ctx.checkpoint().await?;
+            if ctx.should_finish() {
+                return Ok(weights.complete_greedily(&assigned));
+            }
+            ctx.progress(root as f32 / n as f32);
+            ctx.preview(
+                input,
+                &assigned.iter().map(|a| a.unwrap_or(0)).collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    Ok(assigned.into_iter().map(Option::unwrap).collect())
+}
+
+/// The `k` cheapest source pixels for `row`, and the pixel at its own position, which makes
+/// leaving every pixel in place one of the assignments the graph allows.
+fn candidates(
+    weights: &ImgDiffWeights,
+    colors: Option<&ColorGrid>,
+    row: usize,
+    k: usize,
+) -> Vec<Edge> {
+    let mut found = match colors {
+        Some(colors) => colors.nearest(weights, row, k),
+        None => nearest(weights, row, k),
+    };
+    if !found.iter().any(|&(col, _)| col == row) {
+        found.push((row, -weights.at(row, row)));
+    }
+    found
+}
+
+/// The `k` cheapest source pixels for `row`, searching square windows around its position until
+/// everything outside the window is known to cost more.
+fn nearest(weights: &ImgDiffWeights, row: usize, k: usize) -> Vec<Edge> {
+    let sidelen = weights.sidelen;
+    let (x, y) = (row % sidelen, row / sidelen);
+
+    let mut radius = 3;
+    let mut found = Vec::new();
+    loop {
+        found.clear();
+        for sy in y.saturating_sub(radius)..(y + radius + 1).min(sidelen) {
+            for sx in x.saturating_sub(radius)..(x + radius + 1).min(sidelen) {
+                let col = sy * sidelen + sx;
+                found.push((col, -weights.at(row, col)));
+            }
+        }
+        if found.len() >= k {
+            let kth_cost = keep_cheapest(&mut found, k);
+            // anything outside the window is at least radius + 1 away on one axis
+            let outside = weights
+                .model
+                .spatial_cost((radius as i64 + 1).pow(2), weights.proximity_importance);
+            let covers_image = radius >= sidelen;
+            if covers_image || outside >= kth_cost {
+                return found;
+            }
+        }
+        radius *= 2;
+    }
+}
+
+/// Keeps the `k` cheapest of `found` and returns the cost of the most expensive one kept.
+fn keep_cheapest(found: &mut Vec<Edge>, k: usize) -> i64 {
+    found.select_nth_unstable_by_key(k - 1, |&(_, cost)| cost);
+    found.truncate(k);
+    found.iter().map(|&(_, cost)| cost).max().unwrap_or(0)
+}
+
+/// Side of the cells [`ColorGrid`] sorts colors into.
+const COLOR_CELL: i32 = 16;
+
+/// The source pixels sorted into cells by color. Without proximity importance every pixel of
+/// the image is as close as any other, so the candidates are searched around the target color
+/// instead of the position.
+struct ColorGrid {
+    min: CostColor,
+    size: [usize; 3],
+    cells: Vec<Vec<usize>>,
+}
+
+impl ColorGrid {
+    fn new(colors: &[CostColor]) -> Self {
+        let mut min = [i32::MAX; 3];
+        let mut max = [i32::MIN; 3];
+        for color in colors {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(color[axis]);
+                max[axis] = max[axis].max(color[axis]);
+            }
+        }
+        let size = std::array::from_fn(|axis| ((max[axis] - min[axis]) / COLOR_CELL) as usize + 1);
+        let mut grid = Self {
+            min,
+            size,
+            cells: vec![Vec::new(); size.iter().product()],
+        };
+        for (col, &color) in colors.iter().enumerate() {
+            let index = grid.index(grid.cell(color));
+            grid.cells[index].push(col);
+        }
+        grid
+    }
+
+    /// The cell `color` is in, or the closest one for colors outside the grid.
+    fn cell(&self, color: CostColor) -> [usize; 3] {
+        std::array::from_fn(|axis| {
+            ((color[axis] - self.min[axis]).max(0) / COLOR_CELL).min(self.size[axis] as i32 - 1)
+                as usize
+        })
+    }
+
+    fn index(&self, [r, g, b]: [usize; 3]) -> usize {
+        (r * self.size[1] + g) * self.size[2] + b
+    }
+
+    /// Like [`nearest`], searching cubes of cells around the target pixel's color.
+    fn nearest(&self, weights: &ImgDiffWeights, row: usize, k: usize) -> Vec<Edge> {
+        let center = self.cell(weights.target[row]);
+        let span = |axis: usize, radius: usize| {
+            center[axis].saturating_sub(radius)..(center[axis] + radius + 1).min(self.size[axis])
+        };
+
+        let mut radius = 1;
+        let mut found = Vec::new();
+        loop {
+            found.clear();
+            for r in span(0, radius) {
+                for g in span(1, radius) {
+                    for b in span(2, radius) {
+                        for &col in &self.cells[self.index([r, g, b])] {
+                            found.push((col, -weights.at(row, col)));
+                        }
+                    }
+                }
+            }
+            if found.len() >= k {
+                let kth_cost = keep_cheapest(&mut found, k);
+                // anything outside the cube is at least `radius` cells away on one axis
+                let outside = (radius as i64 * COLOR_CELL as i64)
+                    .pow(2)
+                    .saturating_mul(weights.weights[row]);
+                let covers_grid = self.size.iter().all(|&size| radius >= size);
+                if covers_grid || outside >= kth_cost {
+                    return found;
+                }
+            }
+            radius *= 2;
+        }
+    }
+}
+
+/// Scratch space for the shortest path searches, reused between rows.
+struct Search {
+    dist: Vec<i64>,
+    pred: Vec<usize>,
+    scanned: Vec<bool>,
+    touched: Vec<usize>,
+    ready: Vec<usize>,
+    heap: BinaryHeap<Reverse<(i64, usize)>>,
+}
+
+impl Search {
+    fn new(n: usize) -> Self {
+        Self {
+            dist: vec![i64::MAX; n],
+            pred: vec![0; n],
+            scanned: vec![false; n],
+            touched: Vec::new(),
+            ready: Vec::new(),
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Finds the cheapest augmenting path from the unassigned `root` with Dijkstra over reduced
+    /// costs, flips it and lowers the prices of the scanned columns so every assigned row stays
+    /// on its cheapest edge. Returns `false`, changing nothing, if no free column is reachable.
+    fn augment(
+        &mut self,
+        root: usize,
+        edges: &[Vec<Edge>],
+        prices: &mut [i64],
+        owner: &mut [Option<usize>],
+        assigned: &mut [Option<usize>],
+    ) -> bool {
+        for &col in &self.touched {
+            self.dist[col] = i64::MAX;
+            self.scanned[col] = false;
+        }
+        self.touched.clear();
+        self.ready.clear();
+        self.heap.clear();
+
+        for &(col, cost) in &edges[root] {
+            self.relax(col, cost - prices[col], root);
+        }
+
+        let (free, total) = loop {
+            let Some(Reverse((dist, col))) = self.heap.pop() else {
+                return false;
+            };
+            if self.scanned[col] || dist > self.dist[col] {
+                continue;
+            }
+            self.scanned[col] = true;
+            self.ready.push(col);
+            let Some(row) = owner[col] else {
+                break (col, dist);
+            };
+            // the row's current edge is its cheapest, so this keeps the reduced costs >= 0
+            let base = dist - (row_cost(edges, row, col) - prices[col]);
+            for &(next, cost) in &edges[row] {
+                if !self.scanned[next] {
+                    self.relax(next, base + cost - prices[next], row);
+                }
+            }
+        };
+
+        for &col in &self.ready {
+            prices[col] += self.dist[col] - total;
+        }
+
+        let mut col = free;
+        loop {
+            let row = self.pred[col];
+            owner[col] = Some(row);
+            match assigned[row].replace(col) {
+                Some(prev) => col = prev,
+                // only the root was unassigned
+                None => break,
+            }
+        }
+        true
+    }
+
+    fn relax(&mut self, col: usize, dist: i64, from: usize) {
+        if dist < self.dist[col] {
+            if self.dist[col] == i64::MAX {
+                self.touched.push(col);
+            }
+            self.dist[col] = dist;
+            self.pred[col] = from;
+            self.heap.push(Reverse((dist, col)));
+        }
+    }
+}
+
+/// The cost of the edge from `row` to `col`, which must be one of its candidates.
+fn row_cost(edges: &[Vec<Edge>], row: usize, col: usize) -> i64 {
+    edges[row]
+        .iter()
+        .find(|&&(c, _)| c == col)
+        .map(|&(_, cost)| cost)
+        .expect("rows are only assigned along their edges")
+}
@@ -0,0 +1,657 @@
+use crate::core::calculate::cost::{CostModel, MAX_SIDELEN};
+use crate::core::calculate::importance::AutoWeights;
+use crate::core::calculate::solver::SOLVERS;
+use crate::core::calculate::{CalculateError, JobMsg, ProgressMsg};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::preset::Preset;
+use crate::core::preset::UnprocessedPreset;
+use crate::core::validate::ValidationError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::core::validate::validate_preset;
+
+use image::imageops;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::task::{Context, Poll, Waker};
+
+/// Writes a finished preset in the same layout as the bundled `presets/<name>/` directories
+/// and returns the directory it was written to. The name gets a numeric suffix if taken.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_result(
+    presets_dir: &std::path::Path,
+    preset: &Preset,
+    settings: &GenerationSettings,
+) -> Result<std::path::PathBuf, Box<dyn Error>> {
+    validate_preset(preset)?;
+    let base_name = preset.inner.name.clone();
+    let mut dir_name = base_name.clone();
+    let mut counter = 1;
+    while presets_dir.join(&dir_name).exists() {
+        dir_name = format!("{}_{}", base_name, counter);
+        counter += 1;
+    }
+    let dir = presets_dir.join(dir_name);
+    std::fs::create_dir_all(&dir)?;
+
+    let source: SourceImg = image::ImageBuffer::from_vec(
+        preset.inner.width,
+        preset.inner.height,
+        preset.inner.source_img.clone(),
+    )
+    .ok_or("source image does not match its dimensions")?;
+    let source_pixels = source
+        .pixels()
+        .map(|p| (p[0], p[1], p[2]))
+        .collect::<Vec<_>>();
+    let output: SourceImg = image::ImageBuffer::from_vec(
+        preset.inner.width,
+        preset.inner.height,
+        super::make_new_img(&source_pixels, &preset.assignments, preset.inner.width),
+    )
+    .ok_or("assignments do not match the source image")?;
+    let (target, _) = settings.get_target()?;
+
+    output.save(dir.join("output.png"))?;
+    source.save(dir.join("source.png"))?;
+    target.save(dir.join("target.png"))?;
+    std::fs::write(
+        dir.join("assignments.json"),
+        serialize_assignments(&preset.assignments),
+    )?;
+
+    // the full-size custom target is kept as an image rather than inlined into the json
+    let mut settings = settings.clone();
+    if let Some(custom_target) = settings.take_custom_target() {
+        custom_target.save(dir.join("custom_target.png"))?;
+    }
+    std::fs::write(
+        dir.join("settings.json"),
+        serde_json::to_string_pretty(&settings)?,
+    )?;
+    Ok(dir)
+}
+
+/// Reads a preset directory in the layout written by [`save_result`]. Only `source.png` and
+/// `assignments.json` are needed; `settings.json` is picked up if present. The preset is named
+/// after the directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_preset_dir(dir: &std::path::Path) -> Result<Preset, Box<dyn Error>> {
+    let name = dir
+        .file_name()
+        .ok_or("preset directory has no name")?
+        .to_string_lossy()
+        .to_string();
+    let source = image::open(dir.join("source.png"))?.to_rgb8();
+    let assignments = parse_assignments(&std::fs::read_to_string(dir.join("assignments.json"))?)?;
+
+    let settings_path = dir.join("settings.json");
+    let settings = if settings_path.exists() {
+        let mut settings: GenerationSettings =
+            serde_json::from_str(&std::fs::read_to_string(settings_path)?)?;
+        let custom_target_path = dir.join("custom_target.png");
+        if custom_target_path.exists() {
+            settings.set_raw_target(image::open(custom_target_path)?.to_rgb8());
+        }
+        Some(settings)
+    } else {
+        None
+    };
+    let preset = Preset {
+        inner: UnprocessedPreset {
+            name,
+            width: source.width(),
+            height: source.height(),
+            source_img: source.into_raw(),
+        },
+        assignments,
+        settings,
+    };
+    validate_preset(&preset)?;
+    Ok(preset)
+}
+
+/// Loads every preset directory inside `presets_dir`, sorted by path. A missing `presets_dir`
+/// counts as empty; directories that fail to load are returned with their error.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(clippy::type_complexity)]
+pub fn load_presets_dir(
+    presets_dir: &std::path::Path,
+) -> Result<Vec<(std::path::PathBuf, Result<Preset, Box<dyn Error>>)>, Box<dyn Error>> {
+    if !presets_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut dirs = std::fs::read_dir(presets_dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    dirs.retain(|path| path.is_dir());
+    dirs.sort();
+    Ok(dirs
+        .into_iter()
+        .map(|dir| {
+            let preset = load_preset_dir(&dir);
+            (dir, preset)
+        })
+        .collect())
+}
+
+/// Parses the `[a,b,c,...]` format of `assignments.json`.
+pub fn parse_assignments(text: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+    let list = text
+        .trim()
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or("assignments must be a list of indices")?;
+    Ok(list
+        .split(',')
+        .map(|s| s.trim().parse())
+        .collect::<Result<Vec<usize>, _>>()?)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn serialize_assignments(assignments: &[usize]) -> String {
+    format!(
+        "[{}]",
+        assignments
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+pub trait ProgressSink {
+    fn send(&mut self, msg: ProgressMsg);
+}
+// Native-friendly adapter
+impl ProgressSink for std::sync::mpsc::SyncSender<ProgressMsg> {
+    fn send(&mut self, msg: ProgressMsg) {
+        let _ = std::sync::mpsc::SyncSender::send(self, msg);
+    }
+}
+
+/// Sends the messages of one job over a channel shared by all jobs.
+pub struct JobSender {
+    pub job_id: Uuid,
+    pub tx: std::sync::mpsc::SyncSender<JobMsg>,
+}
+
+impl ProgressSink for JobSender {
+    fn send(&mut self, msg: ProgressMsg) {
+        let _ = self.tx.send(JobMsg {
+            job_id: self.job_id,
+            msg,
+        });
+    }
+}
+
+// Allow using closures as progress sinks in WASM
+impl<T> ProgressSink for T
+where
+    T: FnMut(crate::core::calculate::ProgressMsg),
+{
+    fn send(&mut self, msg: crate::core::calculate::ProgressMsg) {
+        self(msg);
+    }
+}
+
+/// Upper limit on the threads a solver may use; 0 means one per core.
+static THREAD_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// Limits the threads the multithreaded solvers use, e.g. to compare against a single thread.
+/// 0, the default, uses one per core.
+pub fn set_thread_limit(threads: usize) {
+    THREAD_LIMIT.store(threads, Ordering::Relaxed);
+}
+
+/// How many threads a solver should use. Always 1 in the browser.
+pub(crate) fn thread_count() -> usize {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        match THREAD_LIMIT.load(Ordering::Relaxed) {
+            0 => cores,
+            limit => limit.min(cores),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    1
+}
+
+/// How often a job running in the web worker hands control back to the worker's event loop.
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+const YIELD_INTERVAL_MS: f64 = 50.0;
+
+/// How the app stops a running job. Cancelling throws the work away; finishing makes the
+/// solver send the best result it has so far; pausing makes it send its state, to be resumed
+/// later.
+#[derive(Debug, Default)]
+pub struct JobControl {
+    cancelled: AtomicBool,
+    finish_requested: AtomicBool,
+    pause_requested: AtomicBool,
+}
+
+impl JobControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn finish(&self) {
+        self.finish_requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_finish_requested(&self) -> bool {
+        self.finish_requested.load(Ordering::Relaxed)
+    }
+
+    pub fn pause(&self) {
+        self.pause_requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_pause_requested(&self) -> bool {
+        self.pause_requested.load(Ordering::Relaxed)
+    }
+}
+
+/// Milliseconds since an arbitrary point, for timing a job.
+fn now_ms() -> f64 {
+    #[cfg(all(target_arch = "wasm32", feature = "web"))]
+    return web_sys::js_sys::Date::now();
+    // without the worker nothing in the browser runs a job
+    #[cfg(all(target_arch = "wasm32", not(feature = "web")))]
+    return 0.0;
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+        * 1000.0;
+}
+
+/// The points between steps of a job where it can be cancelled or told to finish.
+///
+/// On the web the job shares the worker's only thread with the message handler, so a cancel
+/// request can only arrive while the job is suspended here; [`Checkpoint::check`] yields every
+/// [`YIELD_INTERVAL_MS`] for that.
+pub(crate) struct Checkpoint {
+    control: Arc<JobControl>,
+    /// When the job has to finish, if it has a time budget.
+    deadline: Option<f64>,
+    #[cfg(all(target_arch = "wasm32", feature = "web"))]
+    last_yield: f64,
+}
+
+impl Checkpoint {
+    pub fn new(control: Arc<JobControl>, time_budget: Option<u32>) -> Self {
+        Self {
+            control,
+            deadline: time_budget.map(|secs| now_ms() + secs as f64 * 1000.0),
+            #[cfg(all(target_arch = "wasm32", feature = "web"))]
+            last_yield: web_sys::js_sys::Date::now(),
+        }
+    }
+
+    pub fn should_pause(&self) -> bool {
+        self.control.is_pause_requested()
+    }
+
+    /// Whether the job was asked to finish now or has used up its time budget.
+    pub fn should_finish(&self) -> bool {
+        self.control.is_finish_requested()
+            || self.deadline.is_some_and(|deadline| now_ms() >= deadline)
+    }
+
+    /// Sends [`ProgressMsg::Cancelled`] and returns [`CalculateError::Cancelled`] if the job has
+    /// been cancelled.
+    pub async fn check<S: ProgressSink + ?Sized>(
+        &mut self,
+        tx: &mut S,
+    ) -> Result<(), CalculateError> {
+        #[cfg(all(target_arch = "wasm32", feature = "web"))]
+        {
+            let now = web_sys::js_sys::Date::now();
+            if now - self.last_yield >= YIELD_INTERVAL_MS {
+                crate::core::calculate::worker::yield_now().await;
+                self.last_yield = web_sys::js_sys::Date::now();
+            }
+        }
+        if self.control.is_cancelled() {
+            tx.send(ProgressMsg::Cancelled);
+            return Err(CalculateError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// Runs a job on the current thread. Outside the web worker a [`Checkpoint`] never suspends, so
+/// the job completes in a single poll.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn block_on<F: Future>(job: F) -> F::Output {
+    let mut job = std::pin::pin!(job);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(result) = job.as_mut().poll(&mut cx) {
+            return result;
+        }
+    }
+}
+
+/// The source pixels of a job, checked against its dimensions.
+pub(crate) fn source_image(unprocessed: &UnprocessedPreset) -> Result<SourceImg, CalculateError> {
+    if unprocessed.width == 0 || unprocessed.height == 0 {
+        return Err(ValidationError::EmptyImage.into());
+    }
+    image::ImageBuffer::from_vec(
+        unprocessed.width,
+        unprocessed.height,
+        unprocessed.source_img.clone(),
+    )
+    .ok_or_else(|| {
+        ValidationError::ImageDataLength {
+            width: unprocessed.width,
+            height: unprocessed.height,
+            len: unprocessed.source_img.len(),
+        }
+        .into()
+    })
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn get_images(
+    source: SourceImg,
+    settings: &GenerationSettings,
+) -> Result<(Vec<(u8, u8, u8)>, Vec<(u8, u8, u8)>, Vec<i64>), CalculateError> {
+    if settings.sidelen == 0 {
+        return Err(CalculateError::InvalidSettings(
+            "resolution must be at least 1".to_owned(),
+        ));
+    }
+    if settings.sidelen > MAX_SIDELEN {
+        return Err(CalculateError::InvalidSettings(format!(
+            "resolution must be at most {MAX_SIDELEN}"
+        )));
+    }
+    let source = settings.source_crop_scale.apply(&source, settings.sidelen);
+    let source_pixels = source
+        .pixels()
+        .map(|p| (p[0], p[1], p[2]))
+        .collect::<Vec<_>>();
+
+    let (target, weights) = settings.get_target()?;
+    let target_pixels = target
+        .pixels()
+        .map(|p| (p[0], p[1], p[2]))
+        .collect::<Vec<_>>();
+    if source_pixels.len() != target_pixels.len() || weights.len() != target_pixels.len() {
+        return Err(CalculateError::Internal(format!(
+            "cropped images differ in size: {} source pixels, {} target pixels, {} weights",
+            source_pixels.len(),
+            target_pixels.len(),
+            weights.len()
+        )));
+    }
+    Ok((source_pixels, target_pixels, weights))
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct CropScale {
+    pub x: f32,     // -1: all left, 0: center, 1: all right
+    pub y: f32,     // -1: all top, 0: center, 1: all bottom
+    pub scale: f32, // 1: fit within frame, >1: zoom in, <1: not allowed
+}
+
+impl CropScale {
+    pub fn identity() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    pub fn apply(&self, img: &SourceImg, sidelen: u32) -> SourceImg {
+        let (w, h) = img.dimensions();
+
+        let s = self.scale.max(1.0);
+
+        let base_side = w.min(h) as f32;
+        let mut crop_side = (base_side / s).floor().max(1.0);
+
+        crop_side = crop_side.min(w as f32).min(h as f32);
+
+        let max_x_off = (w as f32 - crop_side).max(0.0);
+        let max_y_off = (h as f32 - crop_side).max(0.0);
+
+        let xn = (self.x.clamp(-1.0, 1.0) + 1.0) * 0.5;
+        let yn = (self.y.clamp(-1.0, 1.0) + 1.0) * 0.5;
+
+        let x0 = (xn * max_x_off).floor() as u32;
+        let y0 = (yn * max_y_off).floor() as u32;
+        let cs = crop_side as u32;
+        let cropped = imageops::crop_imm(img, x0, y0, cs, cs).to_image();
+
+        if cs == sidelen {
+            cropped
+        } else {
+            imageops::resize(&cropped, sidelen, sidelen, imageops::FilterType::Lanczos3)
+        }
+    }
+}
+
+/// Which solver in [`SOLVERS`] to run, by the id it was registered with.
+///
+/// Settings store the id, which is written like the variant of an enum, so a new solver only
+/// needs an id of its own and an entry in [`SOLVERS`]. Ids must stay the same once presets have
+/// been saved with them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Algorithm(&'static str);
+
+impl Algorithm {
+    pub const OPTIMAL: Algorithm = Algorithm("Optimal");
+    pub const GENETIC: Algorithm = Algorithm("Genetic");
+    pub const ANNEALING: Algorithm = Algorithm("Annealing");
+
+    /// The algorithm a solver registers as. `id` must be a valid identifier.
+    pub(crate) const fn new(id: &'static str) -> Self {
+        Self(id)
+    }
+
+    pub fn id(self) -> &'static str {
+        self.0
+    }
+
+    /// The algorithm of the solver in [`SOLVERS`] registered as `id`.
+    pub fn from_id(id: &str) -> Option<Self> {
+        SOLVERS
+            .iter()
+            .map(|solver| solver.algorithm())
+            .find(|algorithm| algorithm.0 == id)
+    }
+}
+
+impl Serialize for Algorithm {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // settings are only saved in self-describing formats, which ignore the variant index
+        serializer.serialize_unit_variant("Algorithm", 0, self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Algorithm {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IdVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for IdVisitor {
+            type Value = Algorithm;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("the id of a registered solver")
+            }
+
+            fn visit_enum<A: serde::de::EnumAccess<'de>>(
+                self,
+                data: A,
+            ) -> Result<Algorithm, A::Error> {
+                use serde::de::{Error, VariantAccess};
+                let (id, variant): (String, _) = data.variant()?;
+                variant.unit_variant()?;
+                Algorithm::from_id(&id)
+                    .ok_or_else(|| A::Error::custom(format!("unknown algorithm `{id}`")))
+            }
+        }
+
+        deserializer.deserialize_enum("Algorithm", &[], IdVisitor)
+    }
+}
+
+/// Parameters of the simulated annealing solver.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnnealingSettings {
+    /// Starting temperature, relative to the average cost of a pixel in the starting
+    /// assignment. A swap that makes things worse by `d` is kept with probability `exp(-d / T)`.
+    pub initial_temperature: f64,
+    /// The temperature is multiplied by this after every generation.
+    pub cooling_rate: f64,
+    /// Swaps tried per generation, per pixel.
+    pub swaps_per_pixel: usize,
+    /// Below this temperature, relative like `initial_temperature`, the run may stop.
+    pub final_temperature: f64,
+    /// Once cold enough, the run stops at the first generation keeping fewer swaps than this.
+    pub min_swaps_per_generation: usize,
+}
+
+impl Default for AnnealingSettings {
+    fn default() -> Self {
+        Self {
+            initial_temperature: 0.5,
+            cooling_rate: 0.95,
+            swaps_per_pixel: super::SWAPS_PER_GENERATION_PER_PIXEL,
+            final_temperature: 0.001,
+            min_swaps_per_generation: 10,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GenerationSettings {
+    pub id: Uuid,
+    pub name: String,
+
+    pub proximity_importance: i64,
+    pub algorithm: Algorithm,
+
+    pub sidelen: u32,
+    custom_target: Option<(u32, u32, Vec<u8>)>,
+    pub target_crop_scale: CropScale,
+    pub source_crop_scale: CropScale,
+    #[serde(default)]
+    pub annealing: AnnealingSettings,
+    /// Seconds after which the solver stops and returns the best result it has so far.
+    #[serde(default)]
+    pub time_budget: Option<u32>,
+    /// How the cost of putting a source pixel on a target pixel is computed.
+    #[serde(default)]
+    pub cost_model: CostModel,
+    /// How the importance weights of a custom target are made. Settings saved before there were
+    /// automatic weights keep the uniform weights they were made with.
+    #[serde(default)]
+    pub auto_weights: AutoWeights,
+}
+
+pub type SourceImg = image::RgbImage;
+
+impl GenerationSettings {
+    pub fn default(id: Uuid, name: String) -> Self {
+        Self {
+            name,
+            proximity_importance: 13, // 20
+            algorithm: Algorithm::GENETIC,
+            id,
+            sidelen: 128,
+            custom_target: None,
+            target_crop_scale: CropScale::identity(),
+            source_crop_scale: CropScale::identity(),
+            annealing: AnnealingSettings::default(),
+            time_budget: None,
+            cost_model: CostModel::default(),
+            auto_weights: AutoWeights::automatic(),
+        }
+    }
+
+    /// Proximity importance adjusted for consistency across resolutions.
+    pub fn scaled_proximity_importance(&self) -> i64 {
+        self.proximity_importance_at(self.sidelen)
+    }
+
+    /// The proximity importance for images `sidelen` pixels wide, scaled so distances count the
+    /// same relative to the image size at any resolution.
+    pub fn proximity_importance_at(&self, sidelen: u32) -> i64 {
+        (self.proximity_importance as f32 / (sidelen as f32 / 128.0)) as i64
+    }
+
+    pub fn get_target(&self) -> Result<(SourceImg, Vec<i64>), CalculateError> {
+        let target = self.get_raw_target()?;
+        let target = self.target_crop_scale.apply(&target, self.sidelen);
+        let weights = if self.custom_target.is_some() {
+            self.auto_weights.weights(&target)
+        } else {
+            let target_weights =
+                image::load_from_memory(include_bytes!("weights256.png"))?.to_rgb8();
+            let target_weights = self.target_crop_scale.apply(&target_weights, self.sidelen);
+            load_weights(target_weights)
+        };
+
+        Ok((target, weights))
+    }
+
+    pub fn get_raw_target(&self) -> Result<SourceImg, CalculateError> {
+        if let Some((w, h, data)) = &self.custom_target {
+            image::ImageBuffer::from_vec(*w, *h, data.clone()).ok_or(
+                CalculateError::InvalidTarget {
+                    width: *w,
+                    height: *h,
+                    len: data.len(),
+                },
+            )
+        } else {
+            Ok(image::load_from_memory(include_bytes!("target256.png"))?.to_rgb8())
+        }
+    }
+
+    pub fn set_raw_target(&mut self, img: SourceImg) {
+        let (w, h) = img.dimensions();
+        let data = img.into_raw();
+        self.custom_target = Some((w, h, data));
+    }
+
+    pub fn has_custom_target(&self) -> bool {
+        self.custom_target.is_some()
+    }
+
+    /// Removes the custom target, if any, leaving the built-in one in place. A custom target
+    /// whose data doesn't match its dimensions is dropped.
+    pub fn take_custom_target(&mut self) -> Option<SourceImg> {
+        self.custom_target
+            .take()
+            .and_then(|(w, h, data)| image::ImageBuffer::from_vec(w, h, data))
+    }
+}
+
+pub fn load_weights(source: SourceImg) -> Vec<i64> {
+    let (width, height) = source.dimensions();
+    let mut weights = vec![0; (width * height) as usize];
+    for (x, y, pixel) in source.enumerate_pixels() {
+        let weight = pixel[0] as i64;
+        weights[(y * width + x) as usize] = weight;
+    }
+    weights
+}
@@ -0,0 +1,143 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::DedicatedWorkerGlobalScope;
+use web_sys::js_sys;
+
+#[derive(Serialize, Deserialize)]
+pub enum WorkerReq {
+    Process {
+        source: crate::core::preset::UnprocessedPreset,
+        settings: super::GenerationSettings,
+    },
+    /// Continues optimizing an existing preset, see [`super::refine_async`].
+    Refine {
+        preset: crate::core::preset::Preset,
+        settings: super::GenerationSettings,
+    },
+    /// Carries on with a job that sent `Paused`.
+    Resume { job: super::PausedJob },
+    /// Stops the job with this id at its next checkpoint, which then sends `Cancelled`.
+    Cancel { job_id: Uuid },
+    /// Makes the job with this id send `Done` with its best result so far.
+    Finish { job_id: Uuid },
+    /// Makes the job with this id send `Paused` with its state, if its solver can be paused.
+    Pause { job_id: Uuid },
+}
+
+use crate::core::calculate::util::JobControl;
+use crate::core::calculate::{CalculateError, JobMsg, ProgressMsg};
+use crate::core::calculate::{process_async, refine_async, resume_async};
+use uuid::Uuid;
+
+thread_local! {
+    /// Controls of the jobs currently running in this worker.
+    static RUNNING: RefCell<HashMap<Uuid, Arc<JobControl>>> = RefCell::new(HashMap::new());
+}
+
+/// Lets the worker's event loop run, so requests posted while a job is running get handled.
+pub(crate) async fn yield_now() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let global: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+        let _ = global.set_timeout_with_callback(&resolve);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+enum Job {
+    Process(
+        crate::core::preset::UnprocessedPreset,
+        super::GenerationSettings,
+    ),
+    Refine(crate::core::preset::Preset, super::GenerationSettings),
+    Resume(super::PausedJob),
+}
+
+/// Runs `job` as a task, so the message handler returns and the job can yield to later
+/// requests.
+fn spawn_job(global: &DedicatedWorkerGlobalScope, job: Job) {
+    let global = global.clone();
+    let job_id = match &job {
+        Job::Process(_, settings) | Job::Refine(_, settings) => settings.id,
+        Job::Resume(job) => job.settings.id,
+    };
+    let control = Arc::new(JobControl::default());
+    RUNNING.with_borrow_mut(|running| running.insert(job_id, control.clone()));
+
+    // progress sink -> postMessage
+    let mut sink = move |msg: ProgressMsg| {
+        let msg = JobMsg { job_id, msg };
+        let _ = global.post_message(&serde_wasm_bindgen::to_value(&msg).unwrap());
+    };
+
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = match job {
+            Job::Process(source, settings) => {
+                process_async(source, settings, &mut sink, control).await
+            }
+            Job::Refine(preset, settings) => {
+                refine_async(preset, settings, &mut sink, control).await
+            }
+            Job::Resume(job) => resume_async(job, &mut sink, control).await,
+        };
+        match result {
+            // a cancelled or paused job already said so
+            Ok(()) | Err(CalculateError::Cancelled | CalculateError::Paused) => {}
+            Err(e) => sink(ProgressMsg::Error(e)),
+        }
+        RUNNING.with_borrow_mut(|running| running.remove(&job_id));
+    });
+}
+
+/// Calls `f` on the control of the job with this id, if it is still running.
+fn control_job(job_id: Uuid, f: impl FnOnce(&JobControl)) {
+    RUNNING.with_borrow(|running| {
+        if let Some(control) = running.get(&job_id) {
+            f(control);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn worker_entry() {
+    let global: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let global_for_handler = global.clone();
+
+    let handler = Closure::wrap(Box::new(move |e: web_sys::MessageEvent| {
+        // Deserialize the incoming request
+        let req: WorkerReq = match serde_wasm_bindgen::from_value(e.data()) {
+            Ok(v) => v,
+            Err(err) => {
+                // the job id is unknown without a request, so report it outside any job
+                let msg = JobMsg {
+                    job_id: Uuid::nil(),
+                    msg: ProgressMsg::Error(CalculateError::Internal(format!("bad req: {err}"))),
+                };
+                let _ =
+                    global_for_handler.post_message(&serde_wasm_bindgen::to_value(&msg).unwrap());
+                return;
+            }
+        };
+
+        match req {
+            WorkerReq::Process { source, settings } => {
+                spawn_job(&global_for_handler, Job::Process(source, settings));
+            }
+            WorkerReq::Refine { preset, settings } => {
+                spawn_job(&global_for_handler, Job::Refine(preset, settings));
+            }
+            WorkerReq::Resume { job } => {
+                spawn_job(&global_for_handler, Job::Resume(job));
+            }
+            WorkerReq::Cancel { job_id } => control_job(job_id, JobControl::cancel),
+            WorkerReq::Finish { job_id } => control_job(job_id, JobControl::finish),
+            WorkerReq::Pause { job_id } => control_job(job_id, JobControl::pause),
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    global.set_onmessage(Some(handler.as_ref().unchecked_ref()));
+    handler.forget();
+}
@@ -2,29 +2,49 @@ use std::mem;
 
 use image::ImageBuffer;
 
-use crate::app::{SeedColor, SeedPos, preset::Preset};
+use bytemuck::{Pod, Zeroable};
+
+use crate::core::preset::Preset;
+use crate::core::validate::{ValidationError, validate_assignments, validate_preset};
 
 #[cfg(not(target_arch = "wasm32"))]
-use crate::app::preset::UnprocessedPreset;
+use crate::core::preset::UnprocessedPreset;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct SeedPos {
+    pub xy: [f32; 2],
+}
 
-pub fn init_image(sidelen: u32, source: Preset) -> (u32, Vec<SeedPos>, Vec<SeedColor>, Sim) {
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SeedColor {
+    pub rgba: [f32; 4],
+}
+
+#[allow(clippy::type_complexity)]
+pub fn init_image(
+    sidelen: u32,
+    source: Preset,
+) -> Result<(u32, Vec<SeedPos>, Vec<SeedColor>, Sim), ValidationError> {
+    validate_preset(&source)?;
     let imgpath = image::ImageBuffer::from_vec(
         source.inner.width,
         source.inner.height,
         source.inner.source_img,
     )
-    .unwrap();
+    .expect("validated above");
     let assignments = source.assignments;
 
     let (seeds, colors, seeds_n) = init_colors(sidelen, imgpath);
     let mut sim = Sim::new(source.inner.name);
     sim.cells = vec![CellBody::new(0.0, 0.0, 0.0, 0.0, 0.0); seeds_n];
 
-    sim.set_assignments(assignments, sidelen);
+    sim.set_assignments(assignments, sidelen)?;
     for cell in &mut sim.cells {
         cell.dst_force = 0.14;
     }
-    (seeds_n as u32, seeds, colors, sim)
+    Ok((seeds_n as u32, seeds, colors, sim))
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -32,7 +52,7 @@ pub fn init_canvas(
     sidelen: u32,
     source: UnprocessedPreset,
 ) -> (u32, Vec<SeedPos>, Vec<SeedColor>, Sim) {
-    use crate::app::calculate::drawing_process::DRAWING_CANVAS_SIZE;
+    use crate::core::calculate::drawing_process::DRAWING_CANVAS_SIZE;
     let imgpath =
         image::ImageBuffer::from_vec(source.width, source.height, source.source_img).unwrap();
     let assignments = (0..(DRAWING_CANVAS_SIZE * DRAWING_CANVAS_SIZE)).collect::<Vec<usize>>();
@@ -41,7 +61,8 @@ pub fn init_canvas(
     let mut sim = Sim::new(source.name);
     sim.cells = vec![CellBody::new(0.0, 0.0, 0.0, 0.0, 0.0); seeds_n];
 
-    sim.set_assignments(assignments, sidelen);
+    sim.set_assignments(assignments, sidelen)
+        .expect("identity assignments are a permutation");
     (seeds_n as u32, seeds, colors, sim)
 }
 
@@ -55,7 +76,7 @@ fn init_colors(
     let width = source.width() as usize;
     let height = source.height() as usize;
 
-    assert_eq!(width, height);
+    debug_assert_eq!(width, height);
 
     let seeds_n = width * height;
     let pixelsize = sidelen as f32 / width as f32;
@@ -322,7 +343,14 @@ impl Sim {
         }
     }
 
-    pub fn set_assignments(&mut self, assignments: Vec<usize>, sidelen: u32) {
+    /// Moves every cell to its new destination. Fails without changing anything if
+    /// `assignments` isn't a permutation of the cells.
+    pub fn set_assignments(
+        &mut self,
+        assignments: Vec<usize>,
+        sidelen: u32,
+    ) -> Result<(), ValidationError> {
+        validate_assignments(&assignments, self.cells.len())?;
         let width = (self.cells.len() as f32).sqrt();
         let pixelsize = sidelen as f32 / width;
 
@@ -344,5 +372,6 @@ impl Sim {
             self.cells[*src_idx].age = prev.age;
             self.cells[*src_idx].stroke_id = prev.stroke_id;
         }
+        Ok(())
     }
 }
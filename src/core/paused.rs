@@ -0,0 +1,96 @@
+//! Files holding a paused generation job, so it can be resumed in a later session.
+//!
+//! Same layout as a [`bundle`](crate::core::bundle), with the magic bytes `OBAMJOB\0` and the
+//! sections:
+//!   1. a JSON header with the name and generation settings
+//!   2. the source image as PNG, already cropped and scaled to the job's resolution
+//!   3. the solver state as JSON
+//!   4. the custom target image as PNG, or nothing if the built-in target is used
+
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::bundle::{Reader, encode_png, write_section};
+use crate::core::calculate::PausedJob;
+use crate::core::calculate::util::{GenerationSettings, SourceImg};
+use crate::core::preset::UnprocessedPreset;
+
+/// File extension used for paused jobs, without the dot.
+pub const JOB_EXTENSION: &str = "obamifyjob";
+/// Version written by [`export_job`].
+pub const JOB_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 8] = b"OBAMJOB\0";
+
+#[derive(Serialize, Deserialize)]
+struct JobHeader {
+    name: String,
+    settings: GenerationSettings,
+}
+
+/// Packs a paused job into a file.
+pub fn export_job(job: &PausedJob) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut settings = job.settings.clone();
+    let custom_target = settings.take_custom_target();
+    let header = JobHeader {
+        name: job.source.name.clone(),
+        settings,
+    };
+    let source: SourceImg = image::ImageBuffer::from_vec(
+        job.source.width,
+        job.source.height,
+        job.source.source_img.clone(),
+    )
+    .ok_or("source image does not match its dimensions")?;
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&JOB_VERSION.to_le_bytes());
+    write_section(&mut out, &serde_json::to_vec(&header)?)?;
+    write_section(&mut out, &encode_png(&source)?)?;
+    write_section(&mut out, &serde_json::to_vec(&job.state)?)?;
+    match custom_target {
+        Some(target) => write_section(&mut out, &encode_png(&target)?)?,
+        None => write_section(&mut out, &[])?,
+    }
+    Ok(out)
+}
+
+/// Reads a file written by [`export_job`]. Whether the state fits the job is only checked when
+/// it is resumed.
+pub fn import_job(data: &[u8]) -> Result<PausedJob, Box<dyn Error>> {
+    let data = data
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or("not a paused obamify job")?;
+    let mut reader = Reader { data };
+    let version = reader.u32()?;
+    if version != JOB_VERSION {
+        return Err(format!(
+            "unsupported paused job version {version} (this version reads {JOB_VERSION})"
+        )
+        .into());
+    }
+
+    let JobHeader { name, mut settings } = serde_json::from_slice(reader.section()?)?;
+    let source =
+        image::load_from_memory_with_format(reader.section()?, image::ImageFormat::Png)?.to_rgb8();
+    let state = serde_json::from_slice(reader.section()?)?;
+    let target = reader.section()?;
+    if !target.is_empty() {
+        settings.set_raw_target(
+            image::load_from_memory_with_format(target, image::ImageFormat::Png)?.to_rgb8(),
+        );
+    }
+
+    Ok(PausedJob {
+        source: UnprocessedPreset {
+            name,
+            width: source.width(),
+            height: source.height(),
+            source_img: source.into_raw(),
+        },
+        settings,
+        state,
+    })
+}
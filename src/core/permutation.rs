@@ -0,0 +1,168 @@
+//! Compact binary encoding for assignment permutations.
+//!
+//! Every index is stored with the minimum number of bits needed for the permutation's length,
+//! which is about 1.75 bytes per pixel at 128×128 instead of ~5.5 for the JSON list.
+//!
+//! Layout, all integers little endian:
+//! - format version, `u8`
+//! - number of indices, `u32`
+//! - bits per index, `u8`
+//! - the indices, packed least significant bit first
+//! - CRC-32 of everything before it, `u32`
+
+use std::error::Error;
+
+use crate::core::validate::validate_assignments;
+
+/// Version written by [`encode`].
+pub const FORMAT_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 6;
+const CHECKSUM_LEN: usize = 4;
+
+fn bits_for(len: usize) -> u32 {
+    usize::BITS - len.saturating_sub(1).leading_zeros()
+}
+
+/// Encodes a permutation of `0..assignments.len()`.
+///
+/// Panics if `assignments` has more than `u32::MAX` entries or contains an index out of range.
+pub fn encode(assignments: &[usize]) -> Vec<u8> {
+    let len = u32::try_from(assignments.len()).expect("too many assignments to encode");
+    let bits = bits_for(assignments.len());
+
+    let mut out = Vec::with_capacity(
+        HEADER_LEN + (assignments.len() * bits as usize).div_ceil(8) + CHECKSUM_LEN,
+    );
+    out.push(FORMAT_VERSION);
+    out.extend_from_slice(&len.to_le_bytes());
+    out.push(bits as u8);
+
+    let mut acc = 0u64;
+    let mut acc_bits = 0;
+    for &index in assignments {
+        assert!(index < assignments.len(), "assignment {index} out of range");
+        acc |= (index as u64) << acc_bits;
+        acc_bits += bits;
+        while acc_bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            acc_bits -= 8;
+        }
+    }
+    if acc_bits > 0 {
+        out.push(acc as u8);
+    }
+
+    let checksum = crc32fast::hash(&out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+    out
+}
+
+/// Decodes data written by [`encode`], checking the checksum and that the result is a
+/// permutation.
+pub fn decode(data: &[u8]) -> Result<Vec<usize>, Box<dyn Error>> {
+    if data.len() < HEADER_LEN + CHECKSUM_LEN {
+        return Err("encoded assignments are truncated".into());
+    }
+    let (body, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+    if crc32fast::hash(body) != u32::from_le_bytes(checksum.try_into()?) {
+        return Err("encoded assignments are corrupted (checksum mismatch)".into());
+    }
+
+    let version = body[0];
+    if version != FORMAT_VERSION {
+        return Err(format!("unsupported assignment encoding version {version}").into());
+    }
+    let len = u32::from_le_bytes(body[1..5].try_into()?) as usize;
+    let bits = body[5] as u32;
+    if bits != bits_for(len) {
+        return Err(format!("invalid index width {bits} for {len} assignments").into());
+    }
+    let packed = &body[HEADER_LEN..];
+    if packed.len() as u64 != (len as u64 * bits as u64).div_ceil(8) {
+        return Err("encoded assignments have the wrong length".into());
+    }
+
+    let mask = (1u64 << bits) - 1;
+    let mut assignments = Vec::with_capacity(len);
+    let mut bytes = packed.iter();
+    let mut acc = 0u64;
+    let mut acc_bits = 0;
+    for _ in 0..len {
+        while acc_bits < bits {
+            acc |= (*bytes.next().ok_or("encoded assignments are truncated")? as u64) << acc_bits;
+            acc_bits += 8;
+        }
+        assignments.push((acc & mask) as usize);
+        acc >>= bits;
+        acc_bits -= bits;
+    }
+
+    validate_assignments(&assignments, len)?;
+    Ok(assignments)
+}
+
+/// Serde adapter storing assignments in the compact encoding: base64 text for human readable
+/// formats like RON, raw bytes otherwise. Use with `#[serde(with = "permutation::compact")]`.
+pub mod compact {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::ser::{self, Serializer};
+
+    use crate::core::validate::validate_assignments;
+
+    pub fn serialize<S: Serializer>(assignments: &[usize], s: S) -> Result<S::Ok, S::Error> {
+        // `encode` panics on these, which mustn't take down whatever is being saved around them
+        validate_assignments(assignments, assignments.len()).map_err(ser::Error::custom)?;
+        if u32::try_from(assignments.len()).is_err() {
+            return Err(ser::Error::custom("too many assignments to encode"));
+        }
+        let bytes = super::encode(assignments);
+        if s.is_human_readable() {
+            s.serialize_str(&BASE64.encode(bytes))
+        } else {
+            s.serialize_bytes(&bytes)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<usize>, D::Error> {
+        let bytes = if d.is_human_readable() {
+            d.deserialize_str(EncodedVisitor)?
+        } else {
+            d.deserialize_byte_buf(EncodedVisitor)?
+        };
+        super::decode(&bytes).map_err(de::Error::custom)
+    }
+
+    struct EncodedVisitor;
+
+    impl<'de> Visitor<'de> for EncodedVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("compact encoded assignments")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            BASE64.decode(v).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+            Ok(bytes)
+        }
+    }
+}
@@ -1,9 +1,15 @@
 use serde::{Deserialize, Serialize};
 
+use crate::core::calculate::util::GenerationSettings;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Preset {
     pub inner: UnprocessedPreset,
+    #[serde(with = "crate::core::permutation::compact")]
     pub assignments: Vec<usize>,
+    /// The settings this preset was generated with, if known.
+    #[serde(default)]
+    pub settings: Option<GenerationSettings>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -0,0 +1,142 @@
+//! Versioned envelope for the preset list the app keeps in its persistent storage.
+//!
+//! The list is stored as RON like the rest of the app state, wrapped as
+//! `(version: N, presets: [...])`. Older shapes are read through [`decode_presets`] and migrated
+//! to the current [`Preset`]; data that can't be read is reported instead of being dropped, so the
+//! caller can offer to keep or export it.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::calculate::util::GenerationSettings;
+use crate::core::preset::{Preset, UnprocessedPreset};
+
+/// Storage key of the preset list.
+pub const PRESETS_KEY: &str = "presets";
+/// Storage key of the preset list while the one under [`PRESETS_KEY`] can't be read, so presets
+/// made in the meantime aren't lost. Empty when there is nothing kept apart.
+pub const RECOVERY_PRESETS_KEY: &str = "presets_during_recovery";
+
+/// Version written by [`encode_presets`].
+pub const STORAGE_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    presets: Vec<&'a Preset>,
+}
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeV1 {
+    presets: Vec<PresetV1>,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeV2 {
+    presets: Vec<Preset>,
+}
+
+/// Preset as stored up to version 1, with the assignments as a plain list of indices.
+#[derive(Deserialize)]
+struct PresetV1 {
+    inner: UnprocessedPreset,
+    assignments: Vec<usize>,
+    #[serde(default)]
+    settings: Option<GenerationSettings>,
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// No version could be found in the data.
+    UnknownFormat(String),
+    /// The data claims a version this build doesn't know, e.g. it was written by a newer one.
+    UnsupportedVersion(u32),
+    /// The data couldn't be parsed as the shape its version says it has.
+    Malformed { version: u32, message: String },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::UnknownFormat(message) => {
+                write!(f, "stored presets are in an unknown format: {message}")
+            }
+            StorageError::UnsupportedVersion(version) => write!(
+                f,
+                "stored presets have version {version}, but this version of obamify only reads up to {STORAGE_VERSION}"
+            ),
+            StorageError::Malformed { version, message } => {
+                write!(
+                    f,
+                    "stored presets (version {version}) are malformed: {message}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Writes presets in the current version. Fails on presets whose assignments aren't a
+/// permutation, so validate them first.
+pub fn encode_presets<'a>(
+    presets: impl IntoIterator<Item = &'a Preset>,
+) -> Result<String, ron::Error> {
+    ron::to_string(&Envelope {
+        version: STORAGE_VERSION,
+        presets: presets.into_iter().collect(),
+    })
+}
+
+/// Reads stored presets of any known version, migrating them to the current shape.
+pub fn decode_presets(data: &str) -> Result<Vec<Preset>, StorageError> {
+    // before versioning the list was stored bare, which counts as version 0
+    let version = if data.trim_start().starts_with('[') {
+        0
+    } else {
+        ron::from_str::<VersionProbe>(data)
+            .map_err(|e| StorageError::UnknownFormat(e.to_string()))?
+            .version
+    };
+
+    match version {
+        0 => Ok(migrate_v1(
+            ron::from_str(data).map_err(|e| malformed(0, e))?,
+        )),
+        1 => Ok(migrate_v1(
+            ron::from_str::<EnvelopeV1>(data)
+                .map_err(|e| malformed(1, e))?
+                .presets,
+        )),
+        2 => Ok(ron::from_str::<EnvelopeV2>(data)
+            .map_err(|e| malformed(2, e))?
+            .presets),
+        _ => Err(StorageError::UnsupportedVersion(version)),
+    }
+}
+
+/// Versions 0 (the bare list) and 1 store the assignments as a list of indices, which version 2
+/// replaced with the compact encoding.
+fn migrate_v1(presets: Vec<PresetV1>) -> Vec<Preset> {
+    presets
+        .into_iter()
+        .map(|preset| Preset {
+            inner: preset.inner,
+            assignments: preset.assignments,
+            settings: preset.settings,
+        })
+        .collect()
+}
+
+fn malformed(version: u32, e: impl fmt::Display) -> StorageError {
+    StorageError::Malformed {
+        version,
+        message: e.to_string(),
+    }
+}
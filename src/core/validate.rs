@@ -0,0 +1,126 @@
+//! Checks for presets and assignments coming from outside the solvers (storage, preset folders,
+//! bundles, worker messages), so bad data is reported instead of panicking the morph simulation.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::preset::{Preset, UnprocessedPreset};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValidationError {
+    EmptyImage,
+    /// The morph simulation lays pixels out on a square grid.
+    NotSquare {
+        width: u32,
+        height: u32,
+    },
+    /// The pixel buffer doesn't hold `width * height` RGB pixels.
+    ImageDataLength {
+        width: u32,
+        height: u32,
+        len: usize,
+    },
+    AssignmentCount {
+        expected: usize,
+        actual: usize,
+    },
+    AssignmentOutOfRange {
+        position: usize,
+        index: usize,
+        len: usize,
+    },
+    /// Two target pixels take the same source pixel, so the assignments aren't a permutation.
+    DuplicateAssignment {
+        position: usize,
+        index: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::EmptyImage => write!(f, "the image is empty"),
+            ValidationError::NotSquare { width, height } => {
+                write!(f, "the image is {width}×{height}, but it must be square")
+            }
+            ValidationError::ImageDataLength { width, height, len } => write!(
+                f,
+                "a {width}×{height} image needs {} bytes of pixel data, but has {len}",
+                *width as usize * *height as usize * 3
+            ),
+            ValidationError::AssignmentCount { expected, actual } => write!(
+                f,
+                "expected {expected} assignments for the image, got {actual}"
+            ),
+            ValidationError::AssignmentOutOfRange {
+                position,
+                index,
+                len,
+            } => write!(
+                f,
+                "assignment {position} points to pixel {index}, but the image only has {len}"
+            ),
+            ValidationError::DuplicateAssignment { position, index } => write!(
+                f,
+                "assignments are not a permutation: pixel {index} is used again at {position}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Checks that the image is square and its pixel buffer matches its dimensions.
+pub fn validate_image(img: &UnprocessedPreset) -> Result<(), ValidationError> {
+    if img.width == 0 || img.height == 0 {
+        return Err(ValidationError::EmptyImage);
+    }
+    if img.source_img.len() != img.width as usize * img.height as usize * 3 {
+        return Err(ValidationError::ImageDataLength {
+            width: img.width,
+            height: img.height,
+            len: img.source_img.len(),
+        });
+    }
+    if img.width != img.height {
+        return Err(ValidationError::NotSquare {
+            width: img.width,
+            height: img.height,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `assignments` is a permutation of `0..len`, i.e. every source pixel ends up in
+/// exactly one place.
+pub fn validate_assignments(assignments: &[usize], len: usize) -> Result<(), ValidationError> {
+    if assignments.len() != len {
+        return Err(ValidationError::AssignmentCount {
+            expected: len,
+            actual: assignments.len(),
+        });
+    }
+    let mut seen = vec![false; len];
+    for (position, &index) in assignments.iter().enumerate() {
+        if index >= len {
+            return Err(ValidationError::AssignmentOutOfRange {
+                position,
+                index,
+                len,
+            });
+        }
+        if std::mem::replace(&mut seen[index], true) {
+            return Err(ValidationError::DuplicateAssignment { position, index });
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_preset(preset: &Preset) -> Result<(), ValidationError> {
+    validate_image(&preset.inner)?;
+    validate_assignments(
+        &preset.assignments,
+        preset.inner.width as usize * preset.inner.height as usize,
+    )
+}
@@ -1,6 +1,15 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+#[cfg(all(target_arch = "wasm32", feature = "gui", not(feature = "web")))]
+compile_error!("the `gui` feature needs the `web` feature on wasm32");
+
+#[cfg(feature = "gui")]
 mod app;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+pub mod core;
+
+#[cfg(all(target_arch = "wasm32", feature = "web"))]
+pub use crate::core::calculate::worker::worker_entry;
+#[cfg(feature = "gui")]
 pub use app::ObamifyApp;
-#[cfg(target_arch = "wasm32")]
-pub use app::worker_entry;
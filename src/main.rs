@@ -1,11 +1,29 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-// When compiling natively:
+/// Runs `obamify <command> ...` without opening a window. Returns `false` if no command was given.
 #[cfg(not(target_arch = "wasm32"))]
+fn run_cli() -> bool {
+    match obamify::cli::run_from_args() {
+        None => false,
+        Some(Ok(())) => true,
+        Some(Err(e)) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+// When compiling natively:
+#[cfg(all(not(target_arch = "wasm32"), feature = "gui"))]
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    // `obamify generate ...` runs headless, without opening a window
+    if run_cli() {
+        return Ok(());
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1024.0, 1024.0])
@@ -24,8 +42,19 @@ fn main() -> eframe::Result {
     )
 }
 
+// Without the `gui` feature only the command line mode is available
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "gui")))]
+fn main() {
+    env_logger::init();
+
+    if !run_cli() {
+        eprint!("{}", obamify::cli::usage());
+        std::process::exit(2);
+    }
+}
+
 // When compiling to web using trunk:
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "gui"))]
 fn start_app() {
     use eframe::wasm_bindgen::JsCast as _;
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -103,7 +132,7 @@ fn start_app() {
     });
 }
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "gui"))]
 pub fn main() {
     use wasm_bindgen::JsCast as _;
     console_error_panic_hook::set_once();
@@ -128,3 +157,7 @@ pub fn main() {
         &"Unknown global (not Window / not DedicatedWorkerGlobalScope)".into(),
     );
 }
+
+// On the web without the `gui` feature only the library (and its worker entry) is useful
+#[cfg(all(target_arch = "wasm32", not(feature = "gui")))]
+pub fn main() {}
@@ -0,0 +1,17 @@
+//! Helpers shared by the integration tests and the benchmarks.
+
+use std::path::PathBuf;
+
+use obamify::core::UnprocessedPreset;
+
+/// The cat preset's source image, as a job to process.
+pub fn source() -> UnprocessedPreset {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("presets/cat/source.png");
+    let img = image::open(path).unwrap().to_rgb8();
+    UnprocessedPreset {
+        name: "cat".to_owned(),
+        width: img.width(),
+        height: img.height(),
+        source_img: img.into_raw(),
+    }
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use std::sync::Arc;
+
+use obamify::core::calculate::CalculateError;
+use obamify::core::calculate::cost::{ColorSpace, CostColor, CostModel};
+use obamify::core::{GenerationSettings, JobControl, ProgressMsg, process};
+use uuid::Uuid;
+
+fn model(color_space: ColorSpace) -> CostModel {
+    CostModel {
+        color_space,
+        ..CostModel::default()
+    }
+}
+
+fn distance(a: CostColor, b: CostColor) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(&a, b)| ((a - b) as f64).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[test]
+fn black_and_white_are_as_far_apart_in_every_space() {
+    let rgb = model(ColorSpace::Rgb);
+    let expected = distance(rgb.color((0, 0, 0)), rgb.color((255, 255, 255)));
+    for space in ColorSpace::ALL {
+        let model = model(space);
+        let d = distance(model.color((0, 0, 0)), model.color((255, 255, 255)));
+        assert!((d - expected).abs() <= 1.0, "{}: {d}", space.name());
+    }
+}
+
+#[test]
+fn luminance_ignores_hue() {
+    let model = model(ColorSpace::Luminance);
+    for rgb in [(255, 0, 0), (0, 255, 0), (12, 34, 56)] {
+        let [_, a, b] = model.color(rgb);
+        assert_eq!((a, b), (0, 0));
+    }
+    let [dark, ..] = model.color((40, 40, 40));
+    let [light, ..] = model.color((200, 200, 200));
+    assert!(dark < light);
+}
+
+#[test]
+fn rejects_out_of_range_spatial_exponent() {
+    let mut settings = GenerationSettings::default(Uuid::nil(), "test".to_owned());
+    settings.sidelen = 8;
+    settings.cost_model.spatial_exponent = 0;
+    let result = process(
+        common::source(),
+        settings,
+        &mut |_: ProgressMsg| {},
+        Arc::new(JobControl::default()),
+    );
+    assert!(matches!(result, Err(CalculateError::InvalidSettings(_))));
+}
@@ -0,0 +1,57 @@
+use obamify::core::calculate::importance::{AutoWeights, MIN_WEIGHT};
+use obamify::core::calculate::util::SourceImg;
+
+const SIDE: u32 = 64;
+
+/// A black square on a white background, off to the top left.
+fn square() -> SourceImg {
+    SourceImg::from_fn(SIDE, SIDE, |x, y| {
+        if (8..24).contains(&x) && (8..24).contains(&y) {
+            image::Rgb([0, 0, 0])
+        } else {
+            image::Rgb([255, 255, 255])
+        }
+    })
+}
+
+fn at(weights: &[i64], x: u32, y: u32) -> i64 {
+    weights[(y * SIDE + x) as usize]
+}
+
+#[test]
+fn all_cues_off_is_uniform() {
+    let weights = AutoWeights::uniform().weights(&square());
+    assert!(weights.iter().all(|&w| w == 255));
+}
+
+#[test]
+fn weights_stay_in_range() {
+    let weights = AutoWeights::automatic().weights(&square());
+    assert_eq!(weights.len(), (SIDE * SIDE) as usize);
+    assert!(weights.iter().all(|w| (MIN_WEIGHT..=255).contains(w)));
+}
+
+#[test]
+fn edges_outweigh_flat_regions() {
+    let edges = AutoWeights {
+        edges: 1.0,
+        ..AutoWeights::uniform()
+    };
+    let weights = edges.weights(&square());
+    // on the square's left side, inside it and far away from it
+    assert!(at(&weights, 8, 16) > at(&weights, 16, 16));
+    assert!(at(&weights, 8, 16) > at(&weights, 50, 50));
+    assert_eq!(at(&weights, 50, 50), MIN_WEIGHT);
+}
+
+#[test]
+fn center_bias_peaks_in_the_middle() {
+    let center = AutoWeights {
+        center: 1.0,
+        ..AutoWeights::uniform()
+    };
+    let weights = center.weights(&square());
+    let middle = at(&weights, SIDE / 2, SIDE / 2);
+    assert!(middle > at(&weights, 0, 0));
+    assert!(middle > at(&weights, SIDE - 1, SIDE / 2));
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use std::sync::Arc;
+
+use obamify::core::calculate::CalculateError;
+use obamify::core::paused::{export_job, import_job};
+use obamify::core::{
+    Algorithm, GenerationSettings, JobControl, PausedJob, ProgressMsg, process, resume,
+};
+use uuid::Uuid;
+
+use common::source;
+
+fn settings(algorithm: Algorithm, sidelen: u32) -> GenerationSettings {
+    let mut settings = GenerationSettings::default(Uuid::nil(), "test".to_owned());
+    settings.algorithm = algorithm;
+    settings.sidelen = sidelen;
+    settings
+}
+
+fn uninterrupted(settings: GenerationSettings) -> Vec<usize> {
+    let mut assignments = None;
+    process(
+        source(),
+        settings,
+        &mut |msg: ProgressMsg| {
+            if let ProgressMsg::Done(preset) = msg {
+                assignments = Some(preset.assignments);
+            }
+        },
+        Arc::new(JobControl::default()),
+    )
+    .unwrap();
+    assignments.unwrap()
+}
+
+/// Runs a job until its `pause_at`th progress report, then pauses it.
+fn paused(settings: GenerationSettings, pause_at: usize) -> PausedJob {
+    let control = Arc::new(JobControl::default());
+    let mut reports = 0;
+    let mut paused = None;
+    let result = process(
+        source(),
+        settings,
+        &mut |msg: ProgressMsg| match msg {
+            ProgressMsg::Progress(_) => {
+                reports += 1;
+                if reports == pause_at {
+                    control.pause();
+                }
+            }
+            ProgressMsg::Paused(job) => paused = Some(*job),
+            _ => {}
+        },
+        control.clone(),
+    );
+    assert_eq!(result, Err(CalculateError::Paused));
+    paused.unwrap()
+}
+
+fn resumed(job: PausedJob) -> Vec<usize> {
+    // through a file, like the app does
+    let job = import_job(&export_job(&job).unwrap()).unwrap();
+    let mut assignments = None;
+    resume(
+        job,
+        &mut |msg: ProgressMsg| {
+            if let ProgressMsg::Done(preset) = msg {
+                assignments = Some(preset.assignments);
+            }
+        },
+        Arc::new(JobControl::default()),
+    )
+    .unwrap();
+    assignments.unwrap()
+}
+
+#[test]
+fn resumed_genetic_job_matches_uninterrupted_run() {
+    let settings = settings(Algorithm::GENETIC, 32);
+    let job = paused(settings.clone(), 20);
+    assert_eq!(resumed(job), uninterrupted(settings));
+}
+
+#[test]
+fn resumed_optimal_job_matches_uninterrupted_run() {
+    let settings = settings(Algorithm::OPTIMAL, 16);
+    let job = paused(settings.clone(), 1);
+    assert_eq!(resumed(job), uninterrupted(settings));
+}
+
+#[test]
+fn rejects_state_of_another_solver() {
+    let mut job = paused(settings(Algorithm::GENETIC, 16), 1);
+    job.settings.algorithm = Algorithm::OPTIMAL;
+    let result = resume(
+        job,
+        &mut |_: ProgressMsg| {},
+        Arc::new(JobControl::default()),
+    );
+    assert!(matches!(result, Err(CalculateError::InvalidSettings(_))));
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use obamify::core::calculate::util::parse_assignments;
+use obamify::core::permutation::{decode, encode};
+use obamify::core::storage::encode_presets;
+use obamify::core::{Preset, UnprocessedPreset};
+
+const PRESETS: [&str; 5] = ["wisetree", "blackhole", "cat", "cat2", "colorful"];
+
+fn preset_dir(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("presets")
+        .join(name)
+}
+
+fn json_assignments(name: &str) -> Vec<usize> {
+    let text = std::fs::read_to_string(preset_dir(name).join("assignments.json")).unwrap();
+    parse_assignments(&text).unwrap()
+}
+
+#[test]
+fn round_trips_preset_json() {
+    for name in PRESETS {
+        let assignments = json_assignments(name);
+        let encoded = encode(&assignments);
+        assert_eq!(decode(&encoded).unwrap(), assignments, "{name}");
+        // 14 bits per index at 128×128
+        assert_eq!(encoded.len(), 6 + 128 * 128 * 14 / 8 + 4, "{name}");
+    }
+}
+
+#[test]
+fn bundled_binaries_match_json() {
+    for name in PRESETS {
+        let bin = std::fs::read(preset_dir(name).join("assignments.bin")).unwrap();
+        assert_eq!(decode(&bin).unwrap(), json_assignments(name), "{name}");
+        assert_eq!(encode(&json_assignments(name)), bin, "{name}");
+    }
+}
+
+#[test]
+fn detects_corruption() {
+    let encoded = encode(&json_assignments("cat"));
+    for i in [0, 3, 5, 100, encoded.len() - 1] {
+        let mut corrupted = encoded.clone();
+        corrupted[i] ^= 0x10;
+        assert!(decode(&corrupted).is_err(), "flipped byte {i}");
+    }
+    assert!(decode(&encoded[..encoded.len() - 1]).is_err());
+    assert!(decode(&[]).is_err());
+}
+
+#[test]
+fn rejects_non_permutations() {
+    // a valid checksum doesn't make duplicate indices acceptable
+    assert!(decode(&encode(&[1, 1, 0])).is_err());
+}
+
+#[test]
+fn handles_small_lengths() {
+    for assignments in [vec![], vec![0], vec![1, 0], vec![2, 0, 1]] {
+        assert_eq!(decode(&encode(&assignments)).unwrap(), assignments);
+    }
+}
+
+#[test]
+fn storing_an_invalid_preset_fails_instead_of_panicking() {
+    for assignments in [vec![4; 4], vec![0; 4], vec![0; 3]] {
+        let preset = Preset {
+            inner: UnprocessedPreset {
+                name: "tiny".to_owned(),
+                width: 2,
+                height: 2,
+                source_img: vec![0; 12],
+            },
+            assignments,
+            settings: None,
+        };
+        assert!(encode_presets(&[preset]).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use std::sync::Arc;
+
+use image::RgbImage;
+use obamify::core::calculate::CalculateError;
+use obamify::core::calculate::cost::{CostModel, MAX_COST, MAX_SIDELEN};
+use obamify::core::calculate::importance::AutoWeights;
+use obamify::core::calculate::util::CropScale;
+use obamify::core::validate::validate_assignments;
+use obamify::core::{Algorithm, GenerationSettings, JobControl, ProgressMsg, process};
+use uuid::Uuid;
+
+use common::source;
+
+fn settings(sidelen: u32) -> GenerationSettings {
+    let mut settings = GenerationSettings::default(Uuid::nil(), "test".to_owned());
+    settings.algorithm = Algorithm::GENETIC;
+    settings.sidelen = sidelen;
+    settings
+}
+
+#[test]
+fn costs_grow_with_distance_up_to_the_largest_image() {
+    let far = MAX_SIDELEN - 1;
+    for spatial_exponent in CostModel::SPATIAL_EXPONENTS {
+        let model = CostModel {
+            spatial_exponent,
+            ..CostModel::default()
+        };
+        for proximity_importance in [1, 13, 1_000_000] {
+            let mut last = 0;
+            for d in 0..=far {
+                let cost = model.cost(
+                    (0, 0),
+                    (d, d),
+                    [0, 0, 0],
+                    [255, 255, 255],
+                    255,
+                    proximity_importance,
+                );
+                assert!(
+                    (last..=MAX_COST).contains(&cost),
+                    "exponent {spatial_exponent}, proximity {proximity_importance}, distance {d}"
+                );
+                last = cost;
+            }
+        }
+    }
+    // the costs of every pixel of the largest image add up without overflowing
+    assert!((MAX_SIDELEN as i64).pow(2).checked_mul(MAX_COST).is_some());
+}
+
+#[test]
+fn solves_images_with_more_pixels_than_fit_in_u16_exactly() {
+    let sidelen = 257;
+    let n = (sidelen * sidelen) as usize;
+    assert!(n > u16::MAX as usize);
+
+    let mut settings = settings(sidelen);
+    settings.algorithm = Algorithm::from_id("Sparse").unwrap();
+    settings.auto_weights = AutoWeights::uniform();
+    // far moves cost more than MAX_COST before clamping
+    settings.cost_model.spatial_exponent = 3;
+    // already at the resolution, so the costs below see the same pixels as the solver
+    let target = CropScale::identity().apply(&settings.get_raw_target().unwrap(), sidelen);
+    settings.set_raw_target(target.clone());
+    let source = source();
+    let source_img = RgbImage::from_raw(source.width, source.height, source.source_img.clone());
+    let source_img = CropScale::identity().apply(&source_img.unwrap(), sidelen);
+
+    let mut assignments = None;
+    process(
+        source,
+        settings.clone(),
+        &mut |msg: ProgressMsg| {
+            if let ProgressMsg::Done(preset) = msg {
+                assignments = Some(preset.assignments);
+            }
+        },
+        Arc::new(JobControl::default()),
+    )
+    .unwrap();
+    let assignments = assignments.unwrap();
+    validate_assignments(&assignments, n).unwrap();
+
+    let model = settings.cost_model;
+    let proximity_importance = settings.scaled_proximity_importance();
+    let position = |i: usize| (i as u32 % sidelen, i as u32 / sidelen);
+    let color = |img: &RgbImage, i: usize| {
+        let [r, g, b] = img.get_pixel(position(i).0, position(i).1).0;
+        model.color((r, g, b))
+    };
+    let total_cost = |assignments: &[usize]| {
+        assignments
+            .iter()
+            .enumerate()
+            .try_fold(0i64, |total, (i, &src)| {
+                let cost = model.cost(
+                    position(src),
+                    position(i),
+                    color(&source_img, src),
+                    color(&target, i),
+                    255,
+                    proximity_importance,
+                );
+                assert!((0..=MAX_COST).contains(&cost));
+                total.checked_add(cost)
+            })
+            .expect("the total cost overflows")
+    };
+    let identity = (0..n).collect::<Vec<_>>();
+    assert!(total_cost(&assignments) <= total_cost(&identity));
+    // pixels past the first 65,536 are moved too
+    assert!(
+        assignments[u16::MAX as usize..]
+            .iter()
+            .enumerate()
+            .any(|(i, &src)| src != i + u16::MAX as usize)
+    );
+}
+
+#[test]
+fn rejects_resolutions_past_the_largest() {
+    let result = process(
+        source(),
+        settings(MAX_SIDELEN + 1),
+        &mut |_: ProgressMsg| {},
+        Arc::new(JobControl::default()),
+    );
+    assert!(matches!(result, Err(CalculateError::InvalidSettings(_))));
+}
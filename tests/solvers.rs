@@ -0,0 +1,132 @@
+mod common;
+
+use std::sync::Arc;
+
+use image::RgbImage;
+use obamify::core::calculate::importance::AutoWeights;
+use obamify::core::calculate::util::{AnnealingSettings, CropScale};
+use obamify::core::validate::validate_assignments;
+use obamify::core::{
+    Algorithm, GenerationSettings, JobControl, ProgressMsg, SOLVERS, UnprocessedPreset, process,
+};
+use uuid::Uuid;
+
+const SIDELEN: u32 = 24;
+
+/// The cat and the default target, both already at [`SIDELEN`] and weighted uniformly, so
+/// [`Job::cost`] sees the same pixels and weights as the solvers.
+struct Job {
+    source: UnprocessedPreset,
+    source_img: RgbImage,
+    target: RgbImage,
+    settings: GenerationSettings,
+}
+
+impl Job {
+    fn new(algorithm: Algorithm) -> Self {
+        let mut settings = GenerationSettings::default(Uuid::nil(), "test".to_owned());
+        settings.algorithm = algorithm;
+        settings.sidelen = SIDELEN;
+        settings.auto_weights = AutoWeights::uniform();
+        let target = CropScale::identity().apply(&settings.get_raw_target().unwrap(), SIDELEN);
+        settings.set_raw_target(target.clone());
+
+        let cat = common::source();
+        let cat = RgbImage::from_raw(cat.width, cat.height, cat.source_img).unwrap();
+        let source_img = CropScale::identity().apply(&cat, SIDELEN);
+        let source = UnprocessedPreset {
+            name: "cat".to_owned(),
+            width: SIDELEN,
+            height: SIDELEN,
+            source_img: source_img.as_raw().clone(),
+        };
+        Self {
+            source,
+            source_img,
+            target,
+            settings,
+        }
+    }
+
+    fn solve(&self) -> Vec<usize> {
+        let mut assignments = None;
+        process(
+            self.source.clone(),
+            self.settings.clone(),
+            &mut |msg: ProgressMsg| {
+                if let ProgressMsg::Done(preset) = msg {
+                    assignments = Some(preset.assignments);
+                }
+            },
+            Arc::new(JobControl::default()),
+        )
+        .unwrap();
+        assignments.unwrap()
+    }
+
+    fn cost(&self, assignments: &[usize]) -> i64 {
+        let model = self.settings.cost_model;
+        let proximity_importance = self.settings.scaled_proximity_importance();
+        let position = |i: usize| (i as u32 % SIDELEN, i as u32 / SIDELEN);
+        let color = |img: &RgbImage, i: usize| {
+            let [r, g, b] = img.get_pixel(position(i).0, position(i).1).0;
+            model.color((r, g, b))
+        };
+        assignments
+            .iter()
+            .enumerate()
+            .map(|(i, &src)| {
+                model.cost(
+                    position(src),
+                    position(i),
+                    color(&self.source_img, src),
+                    color(&self.target, i),
+                    255,
+                    proximity_importance,
+                )
+            })
+            .sum()
+    }
+}
+
+#[test]
+fn every_solver_finds_a_permutation_no_worse_than_leaving_pixels_in_place() {
+    let n = (SIDELEN * SIDELEN) as usize;
+    let identity = (0..n).collect::<Vec<_>>();
+    for solver in SOLVERS {
+        let job = Job::new(solver.algorithm());
+        let assignments = job.solve();
+        validate_assignments(&assignments, n).unwrap_or_else(|e| panic!("{}: {e}", solver.name()));
+        assert!(
+            job.cost(&assignments) <= job.cost(&identity),
+            "{} made things worse",
+            solver.name()
+        );
+    }
+}
+
+#[test]
+fn annealing_finishes_with_the_best_assignment_seen() {
+    let mut job = Job::new(Algorithm::ANNEALING);
+    // stops while hot enough that nearly every swap is kept, so where the run has got to is far
+    // worse than where it started
+    job.settings.annealing = AnnealingSettings {
+        initial_temperature: 1000.0,
+        final_temperature: 500.0,
+        min_swaps_per_generation: usize::MAX,
+        ..AnnealingSettings::default()
+    };
+    let assignments = job.solve();
+    let identity = (0..assignments.len()).collect::<Vec<_>>();
+    assert!(job.cost(&assignments) <= job.cost(&identity));
+}
+
+#[test]
+fn sparse_finds_candidates_by_color_without_proximity_importance() {
+    let mut job = Job::new(Algorithm::from_id("Sparse").unwrap());
+    job.settings.proximity_importance = 0;
+    let assignments = job.solve();
+    let identity = (0..assignments.len()).collect::<Vec<_>>();
+    validate_assignments(&assignments, identity.len()).unwrap();
+    assert!(job.cost(&assignments) < job.cost(&identity));
+}
@@ -0,0 +1,102 @@
+use obamify::core::{Algorithm, Preset};
+use obamify::core::preset::UnprocessedPreset;
+use obamify::core::storage::{STORAGE_VERSION, StorageError, decode_presets, encode_presets};
+
+/// The preset list as eframe stored it before it was versioned.
+const BARE_LIST: &str =
+    r#"[(inner:(name:"tiny",width:2,height:1,source_img:[255,0,0,0,0,255]),assignments:[1,0])]"#;
+
+/// Version 1, which added the envelope but still stored the assignments as a list.
+const V1_ENVELOPE: &str = r#"(version:1,presets:[(inner:(name:"tiny",width:2,height:1,source_img:[255,0,0,0,0,255]),assignments:[1,0]),(inner:(name:"other",width:1,height:1,source_img:[1,2,3]),assignments:[0],settings:None)])"#;
+
+fn tiny() -> Preset {
+    Preset {
+        inner: UnprocessedPreset {
+            name: "tiny".to_owned(),
+            width: 2,
+            height: 1,
+            source_img: vec![255, 0, 0, 0, 0, 255],
+        },
+        assignments: vec![1, 0],
+        settings: None,
+    }
+}
+
+fn assert_same(decoded: &Preset, expected: &Preset) {
+    assert_eq!(decoded.inner.name, expected.inner.name);
+    assert_eq!(decoded.inner.width, expected.inner.width);
+    assert_eq!(decoded.inner.height, expected.inner.height);
+    assert_eq!(decoded.inner.source_img, expected.inner.source_img);
+    assert_eq!(decoded.assignments, expected.assignments);
+    assert_eq!(decoded.settings.is_some(), expected.settings.is_some());
+}
+
+#[test]
+fn migrates_the_bare_list() {
+    let presets = decode_presets(BARE_LIST).unwrap();
+    assert_eq!(presets.len(), 1);
+    assert_same(&presets[0], &tiny());
+}
+
+#[test]
+fn migrates_version_1() {
+    let presets = decode_presets(V1_ENVELOPE).unwrap();
+    assert_eq!(presets.len(), 2);
+    assert_same(&presets[0], &tiny());
+    assert_eq!(presets[1].inner.name, "other");
+    assert_eq!(presets[1].assignments, [0]);
+}
+
+#[test]
+fn round_trips_the_current_version() {
+    let encoded = encode_presets(&[tiny()]).unwrap();
+    assert!(encoded.starts_with(&format!("(version:{STORAGE_VERSION},")));
+    let presets = decode_presets(&encoded).unwrap();
+    assert_eq!(presets.len(), 1);
+    assert_same(&presets[0], &tiny());
+
+    // migrated presets are stored in the current version
+    let migrated = decode_presets(V1_ENVELOPE).unwrap();
+    let presets = decode_presets(&encode_presets(&migrated).unwrap()).unwrap();
+    assert_eq!(presets.len(), 2);
+    assert_same(&presets[0], &tiny());
+}
+
+#[test]
+fn rejects_newer_versions() {
+    let newer = format!("(version:{},presets:[])", STORAGE_VERSION + 1);
+    assert!(matches!(
+        decode_presets(&newer),
+        Err(StorageError::UnsupportedVersion(v)) if v == STORAGE_VERSION + 1
+    ));
+}
+
+#[test]
+fn reports_malformed_data() {
+    assert!(matches!(
+        decode_presets("not ron at all"),
+        Err(StorageError::UnknownFormat(_))
+    ));
+    assert!(matches!(
+        decode_presets("[(inner:(name:\"tiny\"),assignments:[0])]"),
+        Err(StorageError::Malformed { version: 0, .. })
+    ));
+    assert!(matches!(
+        decode_presets("(version:1,presets:[(assignments:[0])])"),
+        Err(StorageError::Malformed { version: 1, .. })
+    ));
+    // version 2 stores the assignments compactly, so a plain list is malformed there
+    let v2 = V1_ENVELOPE.replacen("version:1", &format!("version:{STORAGE_VERSION}"), 1);
+    assert!(matches!(
+        decode_presets(&v2),
+        Err(StorageError::Malformed { version: 2, .. })
+    ));
+}
+
+#[test]
+fn reads_algorithms_by_their_registered_id() {
+    let algorithm: Algorithm = serde_json::from_str("\"Genetic\"").unwrap();
+    assert_eq!(Some(algorithm), Algorithm::from_id("Genetic"));
+    assert_eq!(serde_json::to_string(&Algorithm::OPTIMAL).unwrap(), "\"Optimal\"");
+    assert!(serde_json::from_str::<Algorithm>("\"Unregistered\"").is_err());
+}
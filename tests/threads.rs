@@ -0,0 +1,49 @@
+mod common;
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use obamify::core::calculate::util::set_thread_limit;
+use obamify::core::{Algorithm, GenerationSettings, JobControl, ProgressMsg, process};
+use uuid::Uuid;
+
+use common::source;
+
+/// The thread limit is shared by the whole process, so runs that set it take turns.
+static THREAD_LIMIT: Mutex<()> = Mutex::new(());
+
+fn solve(algorithm: Algorithm, sidelen: u32, threads: usize) -> Vec<usize> {
+    let _turn = THREAD_LIMIT.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut settings = GenerationSettings::default(Uuid::nil(), "test".to_owned());
+    settings.algorithm = algorithm;
+    settings.sidelen = sidelen;
+    set_thread_limit(threads);
+    let mut assignments = None;
+    let result = process(
+        source(),
+        settings,
+        &mut |msg: ProgressMsg| {
+            if let ProgressMsg::Done(preset) = msg {
+                assignments = Some(preset.assignments);
+            }
+        },
+        Arc::new(JobControl::default()),
+    );
+    set_thread_limit(0);
+    result.unwrap();
+    assignments.unwrap()
+}
+
+#[test]
+fn parallel_genetic_result_doesnt_depend_on_the_thread_count() {
+    let algorithm = Algorithm::from_id("ParallelGenetic").unwrap();
+    assert_eq!(solve(algorithm, 32, 1), solve(algorithm, 32, 0));
+}
+
+#[test]
+fn parallel_optimal_result_doesnt_depend_on_the_thread_count() {
+    // 48² columns are enough for the solver to split its loops over threads
+    assert_eq!(
+        solve(Algorithm::OPTIMAL, 48, 1),
+        solve(Algorithm::OPTIMAL, 48, 0)
+    );
+}